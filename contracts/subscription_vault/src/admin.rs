@@ -1,12 +1,27 @@
-//! Admin and config: init, min_topup, batch_charge.
+//! Admin and config: init, min_topup, billing operator, batch_charge.
+//!
+//! Config setters apply instantly while the timelock delay is zero (the default).
+//! Once the admin sets a delay, every config change must be queued with
+//! [`do_queue_config_change`] and executed after its `eta`, so subscribers get
+//! notice before terms change. Emergency pause flags are never timelocked.
 //!
 //! **PRs that only change admin or batch behavior should edit this file only.**
 
 use crate::charge_core::charge_one;
-use crate::types::{BatchChargeResult, Error, RecoveryEvent, RecoveryReason};
-use soroban_sdk::{Address, Env, Symbol, Vec};
+use crate::types::{
+    ActionPolicy, BatchChargeResult, ConfigChange, DataKey, Error, LifecycleAction, PauseFlags,
+    PendingAdmin, QueuedConfigChange, RecoveryEvent, RecoveryReason, SubscriptionLimits,
+};
+use soroban_sdk::{token, Address, BytesN, Env, Symbol, Vec};
 
+/// Sets the token, admin and minimum top-up. Fails with
+/// [`Error::AlreadyInitialized`] once the vault has a token or admin, so the
+/// custody asset and admin can never be overwritten.
 pub fn do_init(env: &Env, token: Address, admin: Address, min_topup: i128) -> Result<(), Error> {
+    let instance = env.storage().instance();
+    if instance.has(&Symbol::new(env, "token")) || instance.has(&Symbol::new(env, "admin")) {
+        return Err(Error::AlreadyInitialized);
+    }
     env.storage()
         .instance()
         .set(&Symbol::new(env, "token"), &token);
@@ -23,24 +38,352 @@ pub fn do_init(env: &Env, token: Address, admin: Address, min_topup: i128) -> Re
     Ok(())
 }
 
+/// Returns the single admin address. While an admin council is installed (see
+/// [`crate::council`]) there is none, and every single-admin action fails with
+/// [`Error::CouncilRequired`].
 pub fn require_admin(env: &Env) -> Result<Address, Error> {
+    if crate::council::get_admin_council(env).is_some() {
+        return Err(Error::CouncilRequired);
+    }
     env.storage()
         .instance()
         .get(&Symbol::new(env, "admin"))
         .ok_or(Error::Unauthorized)
 }
 
+/// Checks that `admin` is the admin and that config changes are not timelocked.
+fn require_instant_config(env: &Env, admin: &Address) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if *admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if get_timelock_delay(env) > 0 {
+        return Err(Error::TimelockRequired);
+    }
+    Ok(())
+}
+
 pub fn do_set_min_topup(env: &Env, admin: Address, min_topup: i128) -> Result<(), Error> {
+    require_instant_config(env, &admin)?;
+    apply_config_change(env, ConfigChange::MinTopup(min_topup))
+}
+
+/// Registers `operator` as the billing operator allowed to call the single-charge
+/// entrypoints (`charge_subscription`, `charge_usage`). Only callable by admin.
+pub fn do_set_billing_operator(env: &Env, admin: Address, operator: Address) -> Result<(), Error> {
+    require_instant_config(env, &admin)?;
+    apply_config_change(env, ConfigChange::BillingOperator(operator))
+}
+
+pub fn get_billing_operator(env: &Env) -> Result<Address, Error> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "operator"))
+        .ok_or(Error::NotFound)
+}
+
+/// Returns the address that must authorize single-charge entrypoints: the
+/// registered billing operator, or the admin if none has been set.
+pub fn require_billing_operator(env: &Env) -> Result<Address, Error> {
+    match get_billing_operator(env) {
+        Ok(operator) => Ok(operator),
+        Err(_) => require_admin(env),
+    }
+}
+
+/// Sets which parties may authorize `action`. Only callable by admin.
+pub fn do_set_action_policy(
+    env: &Env,
+    admin: Address,
+    action: LifecycleAction,
+    policy: ActionPolicy,
+) -> Result<(), Error> {
+    require_instant_config(env, &admin)?;
+    apply_config_change(env, ConfigChange::ActionPolicy(action, policy))
+}
+
+/// Returns the policy for `action`, defaulting to subscriber or merchant.
+pub fn get_action_policy(env: &Env, action: LifecycleAction) -> ActionPolicy {
+    env.storage()
+        .instance()
+        .get(&DataKey::ActionPolicy(action))
+        .unwrap_or(ActionPolicy {
+            subscriber: true,
+            merchant: true,
+            admin: false,
+        })
+}
+
+/// Sets the dunning retry schedule: offsets in seconds after the first failed
+/// charge at which an `InsufficientBalance` subscription is retried. When the
+/// last retry fails the subscription becomes `Expired`. An empty schedule
+/// disables dunning. Only callable by admin.
+pub fn do_set_dunning_schedule(
+    env: &Env,
+    admin: Address,
+    retry_offsets: Vec<u64>,
+) -> Result<(), Error> {
+    require_instant_config(env, &admin)?;
+    apply_config_change(env, ConfigChange::DunningSchedule(retry_offsets))
+}
+
+/// Returns the dunning retry offsets (empty if dunning is disabled).
+pub fn get_dunning_schedule(env: &Env) -> Vec<u64> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "dunning"))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Sets the emergency pause flags for charging, deposits and merchant
+/// withdrawals. Only callable by admin.
+pub fn do_set_pause_flags(env: &Env, admin: Address, flags: PauseFlags) -> Result<(), Error> {
     admin.require_auth();
     let stored = require_admin(env)?;
     if admin != stored {
         return Err(Error::Unauthorized);
     }
+    set_pause_flags(env, flags);
+    Ok(())
+}
+
+pub(crate) fn set_pause_flags(env: &Env, flags: PauseFlags) {
     env.storage()
         .instance()
-        .set(&Symbol::new(env, "min_topup"), &min_topup);
+        .set(&Symbol::new(env, "paused"), &flags);
+    env.events()
+        .publish((Symbol::new(env, "pause_updated"),), flags);
+}
+
+/// Returns the emergency pause flags (all clear by default).
+pub fn get_pause_flags(env: &Env) -> PauseFlags {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "paused"))
+        .unwrap_or_default()
+}
+
+/// Fails with [`Error::ContractPaused`] if `paused` is set in the pause flags.
+pub fn require_not_paused(env: &Env, paused: fn(&PauseFlags) -> bool) -> Result<(), Error> {
+    if paused(&get_pause_flags(env)) {
+        return Err(Error::ContractPaused);
+    }
+    Ok(())
+}
+
+/// Sets the bounds on amount and interval enforced when subscriptions and plans
+/// are created. Existing subscriptions are unaffected. Only callable by admin.
+pub fn do_set_subscription_limits(
+    env: &Env,
+    admin: Address,
+    limits: SubscriptionLimits,
+) -> Result<(), Error> {
+    require_instant_config(env, &admin)?;
+    apply_config_change(env, ConfigChange::SubscriptionLimits(limits))
+}
+
+/// Returns the subscription limits, defaulting to any positive amount and any
+/// non-zero interval.
+pub fn get_subscription_limits(env: &Env) -> SubscriptionLimits {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "limits"))
+        .unwrap_or(SubscriptionLimits {
+            min_amount: 1,
+            max_amount: i128::MAX,
+            min_interval_seconds: 1,
+            max_interval_seconds: u64::MAX,
+        })
+}
+
+/// Rejects a config change that could not be applied: a dunning schedule that is
+/// not strictly increasing from above zero, or inconsistent subscription limits.
+fn validate_config_change(change: &ConfigChange) -> Result<(), Error> {
+    match change {
+        ConfigChange::DunningSchedule(retry_offsets) => {
+            let mut prev = 0u64;
+            for offset in retry_offsets.iter() {
+                if offset <= prev {
+                    return Err(Error::InvalidDunningSchedule);
+                }
+                prev = offset;
+            }
+        }
+        ConfigChange::SubscriptionLimits(limits) => {
+            if limits.min_amount <= 0
+                || limits.min_amount > limits.max_amount
+                || limits.min_interval_seconds == 0
+                || limits.min_interval_seconds > limits.max_interval_seconds
+            {
+                return Err(Error::InvalidLimits);
+            }
+        }
+        ConfigChange::MinTopup(_)
+        | ConfigChange::BillingOperator(_)
+        | ConfigChange::ActionPolicy(_, _)
+        | ConfigChange::TimelockDelay(_) => {}
+    }
+    Ok(())
+}
+
+/// Validates and writes a config change, publishing its `*_updated` event.
+/// Callers must have checked authorization (or the timelock).
+fn apply_config_change(env: &Env, change: ConfigChange) -> Result<(), Error> {
+    validate_config_change(&change)?;
+    let instance = env.storage().instance();
+    match change {
+        ConfigChange::MinTopup(min_topup) => {
+            instance.set(&Symbol::new(env, "min_topup"), &min_topup);
+            env.events()
+                .publish((Symbol::new(env, "min_topup_updated"),), min_topup);
+        }
+        ConfigChange::BillingOperator(operator) => {
+            instance.set(&Symbol::new(env, "operator"), &operator);
+            env.events()
+                .publish((Symbol::new(env, "operator_updated"),), operator);
+        }
+        ConfigChange::ActionPolicy(action, policy) => {
+            instance.set(&DataKey::ActionPolicy(action.clone()), &policy);
+            env.events()
+                .publish((Symbol::new(env, "policy_updated"), action), policy);
+        }
+        ConfigChange::DunningSchedule(retry_offsets) => {
+            instance.set(&Symbol::new(env, "dunning"), &retry_offsets);
+            env.events()
+                .publish((Symbol::new(env, "dunning_updated"),), retry_offsets);
+        }
+        ConfigChange::SubscriptionLimits(limits) => {
+            instance.set(&Symbol::new(env, "limits"), &limits);
+            env.events()
+                .publish((Symbol::new(env, "limits_updated"),), limits);
+        }
+        ConfigChange::TimelockDelay(delay) => {
+            instance.set(&Symbol::new(env, "timelock"), &delay);
+            env.events()
+                .publish((Symbol::new(env, "timelock_updated"),), delay);
+        }
+    }
+    Ok(())
+}
+
+/// Minimum notice in seconds between queuing a config change and its `eta`.
+/// Zero (the default) also allows the instant setters.
+pub fn get_timelock_delay(env: &Env) -> u64 {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "timelock"))
+        .unwrap_or(0)
+}
+
+/// Returns all queued config changes in the order they were queued.
+pub fn get_queued_config_changes(env: &Env) -> Vec<QueuedConfigChange> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "queued"))
+        .unwrap_or(Vec::new(env))
+}
+
+/// Queues `change` to take effect at `eta`, which must be at least the timelock
+/// delay from now. Returns the change id. Only callable by admin.
+pub fn do_queue_config_change(
+    env: &Env,
+    admin: Address,
+    change: ConfigChange,
+    eta: u64,
+) -> Result<u32, Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    validate_config_change(&change)?;
+    let earliest = env
+        .ledger()
+        .timestamp()
+        .checked_add(get_timelock_delay(env))
+        .ok_or(Error::Overflow)?;
+    if eta < earliest {
+        return Err(Error::EtaTooEarly);
+    }
+    Ok(queue_change(env, change, eta))
+}
+
+/// Applies `change` now if no timelock delay is set, otherwise queues it for
+/// the earliest allowed `eta`. Callers must have checked authorization.
+pub(crate) fn schedule_config_change(env: &Env, change: ConfigChange) -> Result<(), Error> {
+    let delay = get_timelock_delay(env);
+    if delay == 0 {
+        return apply_config_change(env, change);
+    }
+    validate_config_change(&change)?;
+    let eta = env
+        .ledger()
+        .timestamp()
+        .checked_add(delay)
+        .ok_or(Error::Overflow)?;
+    queue_change(env, change, eta);
+    Ok(())
+}
+
+fn queue_change(env: &Env, change: ConfigChange, eta: u64) -> u32 {
+    let id_key = Symbol::new(env, "next_change_id");
+    let id: u32 = env.storage().instance().get(&id_key).unwrap_or(0);
+    env.storage().instance().set(&id_key, &(id + 1));
+
+    let queued = QueuedConfigChange { id, change, eta };
+    let mut all = get_queued_config_changes(env);
+    all.push_back(queued.clone());
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "queued"), &all);
+    env.events()
+        .publish((Symbol::new(env, "config_queued"), id), queued);
+    id
+}
+
+/// Removes queued change `id`, returning it. `NotFound` if it is not queued.
+fn take_queued_change(env: &Env, id: u32) -> Result<QueuedConfigChange, Error> {
+    let mut all = get_queued_config_changes(env);
+    let index = all
+        .iter()
+        .position(|queued| queued.id == id)
+        .ok_or(Error::NotFound)?;
+    let queued = all.get_unchecked(index as u32);
+    all.remove(index as u32);
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "queued"), &all);
+    Ok(queued)
+}
+
+/// Applies queued change `id` once its `eta` has passed. Callable by anyone.
+pub fn do_execute_config_change(env: &Env, id: u32) -> Result<(), Error> {
+    let queued = take_queued_change(env, id)?;
+    if env.ledger().timestamp() < queued.eta {
+        return Err(Error::TimelockNotElapsed);
+    }
+    apply_config_change(env, queued.change)?;
+    env.events()
+        .publish((Symbol::new(env, "config_executed"), id), ());
+    Ok(())
+}
+
+/// Drops queued change `id` without applying it. Only callable by admin.
+pub fn do_cancel_config_change(env: &Env, admin: Address, id: u32) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    cancel_config_change(env, id)
+}
+
+/// Drops queued change `id`. Callers must have checked authorization.
+pub(crate) fn cancel_config_change(env: &Env, id: u32) -> Result<(), Error> {
+    take_queued_change(env, id)?;
     env.events()
-        .publish((Symbol::new(env, "min_topup_updated"),), min_topup);
+        .publish((Symbol::new(env, "config_cancelled"), id), ());
     Ok(())
 }
 
@@ -51,31 +394,66 @@ pub fn get_min_topup(env: &Env) -> Result<i128, Error> {
         .ok_or(Error::NotFound)
 }
 
+/// Returns the SEP-41 token (e.g. USDC) the vault holds, as configured in [`do_init`].
+pub fn get_token(env: &Env) -> Result<Address, Error> {
+    env.storage()
+        .instance()
+        .get(&Symbol::new(env, "token"))
+        .ok_or(Error::NotFound)
+}
+
+/// Returns the address that must authorize batch charges: the admin, or under an
+/// admin council the registered billing operator.
+fn require_batch_charger(env: &Env) -> Result<Address, Error> {
+    match require_admin(env) {
+        Err(Error::CouncilRequired) => {
+            get_billing_operator(env).map_err(|_| Error::CouncilRequired)
+        }
+        other => other,
+    }
+}
+
 pub fn do_batch_charge(
     env: &Env,
     subscription_ids: &Vec<u32>,
 ) -> Result<Vec<BatchChargeResult>, Error> {
-    let auth_admin = require_admin(env)?;
-    auth_admin.require_auth();
+    require_batch_charger(env)?.require_auth();
 
     let mut results = Vec::new(env);
     for id in subscription_ids.iter() {
-        let r = charge_one(env, id, None);
-        let res = match &r {
-            Ok(()) => BatchChargeResult {
-                success: true,
-                error_code: 0,
-            },
-            Err(e) => BatchChargeResult {
-                success: false,
-                error_code: e.clone().to_code(),
-            },
-        };
-        results.push_back(res);
+        results.push_back(to_batch_result(charge_one(env, id, None)));
+    }
+    Ok(results)
+}
+
+/// Like [`do_batch_charge`], but each subscription is charged with its paired
+/// idempotency key so a retried batch does not double-charge.
+pub fn do_batch_charge_idempotent(
+    env: &Env,
+    charges: &Vec<(u32, BytesN<32>)>,
+) -> Result<Vec<BatchChargeResult>, Error> {
+    require_batch_charger(env)?.require_auth();
+
+    let mut results = Vec::new(env);
+    for (id, key) in charges.iter() {
+        results.push_back(to_batch_result(charge_one(env, id, Some(key))));
     }
     Ok(results)
 }
 
+fn to_batch_result(r: Result<(), Error>) -> BatchChargeResult {
+    match r {
+        Ok(()) => BatchChargeResult {
+            success: true,
+            error_code: 0,
+        },
+        Err(e) => BatchChargeResult {
+            success: false,
+            error_code: e.to_code(),
+        },
+    }
+}
+
 pub fn do_get_admin(env: &Env) -> Result<Address, Error> {
     env.storage()
         .instance()
@@ -83,15 +461,118 @@ pub fn do_get_admin(env: &Env) -> Result<Address, Error> {
         .ok_or(Error::NotFound)
 }
 
-pub fn do_rotate_admin(env: &Env, current_admin: Address, new_admin: Address) -> Result<(), Error> {
+/// Proposes `new_admin` as the next admin. Nothing changes until the proposal
+/// is accepted with [`do_accept_admin`]; a new proposal replaces the pending one.
+/// Only callable by admin.
+pub fn do_propose_admin(
+    env: &Env,
+    current_admin: Address,
+    new_admin: Address,
+    expires_at: Option<u64>,
+) -> Result<(), Error> {
+    current_admin.require_auth();
+    let stored = require_admin(env)?;
+    if current_admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    if expires_at.is_some_and(|t| t <= env.ledger().timestamp()) {
+        return Err(Error::AdminProposalExpired);
+    }
+    store_pending_admin(env, current_admin, new_admin, expires_at);
+    Ok(())
+}
+
+/// Records `new_admin` as the pending admin, replacing any earlier proposal.
+/// Callers must have checked authorization.
+pub(crate) fn store_pending_admin(
+    env: &Env,
+    proposer: Address,
+    new_admin: Address,
+    expires_at: Option<u64>,
+) {
+    env.storage().instance().set(
+        &Symbol::new(env, "pending_admin"),
+        &PendingAdmin {
+            admin: new_admin.clone(),
+            expires_at,
+        },
+    );
+    env.events().publish(
+        (Symbol::new(env, "admin_proposed"), proposer),
+        (new_admin, expires_at),
+    );
+}
+
+/// Completes a pending admin transfer. Must be authorized by the proposed admin
+/// before the proposal expires. A transfer proposed by the admin council
+/// dissolves the council.
+pub fn do_accept_admin(env: &Env, new_admin: Address) -> Result<(), Error> {
+    new_admin.require_auth();
+    let pending = get_pending_admin(env).ok_or(Error::NotFound)?;
+    if pending.admin != new_admin {
+        return Err(Error::Unauthorized);
+    }
+    if pending
+        .expires_at
+        .is_some_and(|t| env.ledger().timestamp() >= t)
+    {
+        return Err(Error::AdminProposalExpired);
+    }
+
+    let old_admin = match require_admin(env) {
+        Err(Error::CouncilRequired) => {
+            env.storage()
+                .instance()
+                .remove(&Symbol::new(env, "council"));
+            env.events()
+                .publish((Symbol::new(env, "council_dissolved"),), new_admin.clone());
+            env.current_contract_address()
+        }
+        other => other?,
+    };
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "admin"), &new_admin);
+    env.storage()
+        .instance()
+        .remove(&Symbol::new(env, "pending_admin"));
+    env.events().publish(
+        (Symbol::new(env, "admin_accepted"), new_admin.clone()),
+        (old_admin, new_admin, env.ledger().timestamp()),
+    );
+    Ok(())
+}
+
+/// Withdraws the pending admin proposal. Only callable by admin.
+pub fn do_cancel_admin_proposal(env: &Env, current_admin: Address) -> Result<(), Error> {
     current_admin.require_auth();
+    let stored = require_admin(env)?;
+    if current_admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    let pending = get_pending_admin(env).ok_or(Error::NotFound)?;
+    env.storage()
+        .instance()
+        .remove(&Symbol::new(env, "pending_admin"));
+    env.events().publish(
+        (Symbol::new(env, "admin_proposal_cancelled"), current_admin),
+        pending.admin,
+    );
+    Ok(())
+}
 
-    let stored_admin: Address = env
-        .storage()
+pub fn get_pending_admin(env: &Env) -> Option<PendingAdmin> {
+    env.storage()
         .instance()
-        .get(&Symbol::new(env, "admin"))
-        .ok_or(Error::NotFound)?;
+        .get(&Symbol::new(env, "pending_admin"))
+}
 
+/// Replaces the admin in one step. Only compiled for tests (`testutils`); live
+/// deployments use [`do_propose_admin`] and [`do_accept_admin`].
+#[cfg(any(test, feature = "testutils"))]
+pub fn do_rotate_admin(env: &Env, current_admin: Address, new_admin: Address) -> Result<(), Error> {
+    current_admin.require_auth();
+    let stored_admin = require_admin(env)?;
     if current_admin != stored_admin {
         return Err(Error::Unauthorized);
     }
@@ -116,20 +597,29 @@ pub fn do_recover_stranded_funds(
     reason: RecoveryReason,
 ) -> Result<(), Error> {
     admin.require_auth();
-
-    let stored_admin: Address = env
-        .storage()
-        .instance()
-        .get(&Symbol::new(env, "admin"))
-        .ok_or(Error::NotFound)?;
-
-    if admin != stored_admin {
+    let stored = require_admin(env)?;
+    if admin != stored {
         return Err(Error::Unauthorized);
     }
+    recover_funds(env, admin, recipient, amount, reason)
+}
 
+/// Performs a recovery on behalf of `admin` (the contract itself for a council
+/// action): transfers `amount` of the vault token to `recipient`, which must not
+/// exceed [`get_recoverable_surplus`]. Callers must have checked authorization.
+pub(crate) fn recover_funds(
+    env: &Env,
+    admin: Address,
+    recipient: Address,
+    amount: i128,
+    reason: RecoveryReason,
+) -> Result<(), Error> {
     if amount <= 0 {
         return Err(Error::InvalidRecoveryAmount);
     }
+    if amount > get_recoverable_surplus(env)? {
+        return Err(Error::ExceedsSurplus);
+    }
 
     let recovery_event = RecoveryEvent {
         admin: admin.clone(),
@@ -144,8 +634,47 @@ pub fn do_recover_stranded_funds(
         recovery_event,
     );
 
-    // TODO: Actual token transfer logic
-    // token_client.transfer(&env.current_contract_address(), &recipient, &amount);
+    token::Client::new(env, &get_token(env)?).transfer(
+        &env.current_contract_address(),
+        &recipient,
+        &amount,
+    );
+    Ok(())
+}
 
+fn adjust_total(env: &Env, name: &str, delta: i128) -> Result<(), Error> {
+    let key = Symbol::new(env, name);
+    let total: i128 = env.storage().instance().get(&key).unwrap_or(0);
+    let total = total.checked_add(delta).ok_or(Error::Overflow)?;
+    env.storage().instance().set(&key, &total);
     Ok(())
 }
+
+/// Adds `delta` to the running total of all subscribers' `prepaid_balance`.
+/// Must be called wherever a prepaid balance changes.
+pub(crate) fn adjust_total_prepaid(env: &Env, delta: i128) -> Result<(), Error> {
+    adjust_total(env, "total_prepaid", delta)
+}
+
+/// Adds `delta` to the running total of all merchants' earned balances.
+/// Must be called wherever a merchant ledger changes.
+pub(crate) fn adjust_total_merchant(env: &Env, delta: i128) -> Result<(), Error> {
+    adjust_total(env, "total_merchant", delta)
+}
+
+/// Vault tokens not owed to anyone: the contract's token balance minus all
+/// prepaid balances and merchant earnings (never negative). Only this can be
+/// recovered.
+pub fn get_recoverable_surplus(env: &Env) -> Result<i128, Error> {
+    let balance =
+        token::Client::new(env, &get_token(env)?).balance(&env.current_contract_address());
+    let owed = [
+        Symbol::new(env, "total_prepaid"),
+        Symbol::new(env, "total_merchant"),
+    ]
+    .iter()
+    .map(|key| env.storage().instance().get::<_, i128>(key).unwrap_or(0))
+    .try_fold(0i128, |sum, total| sum.checked_add(total))
+    .ok_or(Error::Overflow)?;
+    Ok(balance.saturating_sub(owed).max(0))
+}
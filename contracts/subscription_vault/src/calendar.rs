@@ -0,0 +1,124 @@
+//! Billing interval arithmetic: fixed-length periods and calendar months/years
+//! computed from ledger timestamps (UTC).
+//!
+//! **PRs that only change how billing periods are measured should edit this file only.**
+//!
+//! Calendar periods are measured from the subscription's `billing_anchor`, not
+//! from the previous period, so a subscription anchored on Jan 31 bills on
+//! Feb 28 (or 29), Mar 31, Apr 30, ... without drifting to the 28th.
+
+use crate::types::BillingInterval;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+/// Converts days since 1970-01-01 to a proleptic Gregorian `(year, month, day)`.
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+/// Converts a proleptic Gregorian date to days since 1970-01-01.
+fn days_from_civil(year: i64, month: u32, day: u32) -> i64 {
+    let y = year - i64::from(month <= 2);
+    let era = y.div_euclid(400);
+    let yoe = y.rem_euclid(400);
+    let m = i64::from(month);
+    let doy = (153 * (if m > 2 { m - 3 } else { m + 9 }) + 2) / 5 + i64::from(day) - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: u32) -> u32 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Months since year 0 (`year * 12 + month - 1`) of the date containing `timestamp`.
+fn month_index(timestamp: u64) -> i64 {
+    let (year, month, _) = civil_from_days((timestamp / SECONDS_PER_DAY) as i64);
+    year * 12 + i64::from(month) - 1
+}
+
+/// Adds `months` calendar months to `timestamp`, keeping the time of day and
+/// clamping the day to the last day of the target month.
+fn add_months(timestamp: u64, months: u64) -> Option<u64> {
+    let days = (timestamp / SECONDS_PER_DAY) as i64;
+    let (year, month, day) = civil_from_days(days);
+    let target = (year * 12 + i64::from(month) - 1).checked_add(i64::try_from(months).ok()?)?;
+    let target_year = target.div_euclid(12);
+    let target_month = target.rem_euclid(12) as u32 + 1;
+    let target_day = day.min(days_in_month(target_year, target_month));
+    let target_days = u64::try_from(days_from_civil(target_year, target_month, target_day)).ok()?;
+    target_days
+        .checked_mul(SECONDS_PER_DAY)?
+        .checked_add(timestamp % SECONDS_PER_DAY)
+}
+
+impl BillingInterval {
+    /// Length of one period in seconds, or `None` for calendar intervals.
+    fn fixed_seconds(&self) -> Option<u64> {
+        match *self {
+            BillingInterval::Seconds(seconds) => Some(seconds),
+            BillingInterval::Days(days) => Some(u64::from(days) * SECONDS_PER_DAY),
+            BillingInterval::CalendarMonths(_) | BillingInterval::CalendarYears(_) => None,
+        }
+    }
+
+    /// Number of calendar months per period, or `None` for fixed-length intervals.
+    fn calendar_months(&self) -> Option<u64> {
+        match *self {
+            BillingInterval::CalendarMonths(months) => Some(u64::from(months)),
+            BillingInterval::CalendarYears(years) => Some(u64::from(years) * 12),
+            BillingInterval::Seconds(_) | BillingInterval::Days(_) => None,
+        }
+    }
+
+    /// Start timestamp of billing period `period` for a subscription anchored at
+    /// `anchor`. `None` on overflow.
+    pub fn period_start(&self, anchor: u64, period: u64) -> Option<u64> {
+        match self.fixed_seconds() {
+            Some(seconds) => period.checked_mul(seconds)?.checked_add(anchor),
+            None => add_months(anchor, period.checked_mul(self.calendar_months()?)?),
+        }
+    }
+
+    /// Length in seconds of the first period for a subscription anchored at
+    /// `anchor`. `None` on overflow.
+    pub fn first_period_seconds(&self, anchor: u64) -> Option<u64> {
+        self.period_start(anchor, 1)?.checked_sub(anchor)
+    }
+
+    /// Index of the billing period containing `timestamp` for a subscription
+    /// anchored at `anchor`. `None` before the anchor or for a zero-length interval.
+    pub fn period_at(&self, anchor: u64, timestamp: u64) -> Option<u64> {
+        let elapsed = timestamp.checked_sub(anchor)?;
+        if let Some(seconds) = self.fixed_seconds() {
+            return elapsed.checked_div(seconds);
+        }
+
+        let per_period = self.calendar_months()?;
+        let months = u64::try_from(month_index(timestamp) - month_index(anchor)).ok()?;
+        let mut period = months.checked_div(per_period)?;
+        // Within the final month the (clamped) period start may still be ahead.
+        if period > 0 && self.period_start(anchor, period)? > timestamp {
+            period -= 1;
+        }
+        Some(period)
+    }
+}
@@ -2,63 +2,132 @@
 //!
 //! **PRs that only change how one subscription is charged should edit this file only.**
 //!
+//! Every successful debit of a subscriber's `prepaid_balance` credits the merchant's
+//! earned balance (see [`crate::merchant::credit_merchant_balance`]) by the same amount.
+//!
 //! # Replay protection and idempotency
 //!
 //! Charges are protected against replay by:
-//! - **Period-based key**: We record the last charged billing period index per subscription.
+//! - **Period-based key**: We record the last charged billing period index per subscription,
+//!   counted in whole intervals from the subscription's `billing_anchor`.
 //!   A charge for the same period is rejected with [`Error::Replay`].
 //! - **Optional idempotency key**: If the caller supplies an idempotency key (e.g. for retries),
 //!   we store one key per subscription. A second call with the same key returns `Ok(())` without
 //!   debiting again (idempotent success). Storage stays bounded (one key and one period per sub).
+//! - **Usage report sequence**: every usage charge carries a metering report sequence number
+//!   that must be strictly greater than the last accepted one for the subscription (`useq`).
+//!   A reused or older sequence is rejected with [`Error::Replay`]. One `u64` per sub.
+//! - **Dunning state**: an `InsufficientBalance` subscription under a dunning schedule keeps
+//!   one [`DunningState`] (`dun`) recording the first failure and failed retry count.
+//! - **Usage idempotency key**: usage charges keep their own last-seen key per subscription
+//!   (`uidem`), so a retried metering report returns success instead of `Replay`.
 
+use crate::admin::{adjust_total_prepaid, get_dunning_schedule, require_not_paused};
+use crate::merchant::credit_merchant_balance;
 use crate::queries::get_subscription;
 use crate::state_machine::validate_status_transition;
-use crate::types::{Error, SubscriptionChargedEvent, SubscriptionStatus};
-use soroban_sdk::{symbol_short, Env, Symbol};
+use crate::storage::{get_persistent, set_persistent};
+use crate::types::{
+    CatchUpPolicy, DunningState, Error, Subscription, SubscriptionChargedEvent,
+    SubscriptionExpiredEvent, SubscriptionStatus, TrialConvertedEvent,
+};
+use soroban_sdk::{symbol_short, Env, Symbol, Vec};
 
 const KEY_CHARGED_PERIOD: Symbol = symbol_short!("cp");
 const KEY_IDEM: Symbol = symbol_short!("idem");
+const KEY_USAGE_IDEM: Symbol = symbol_short!("uidem");
+const KEY_USAGE_SEQ: Symbol = symbol_short!("useq");
+const KEY_DUNNING: Symbol = symbol_short!("dun");
 
-fn charged_period_key(subscription_id: u32) -> (Symbol, u32) {
+pub(crate) fn charged_period_key(subscription_id: u32) -> (Symbol, u32) {
     (KEY_CHARGED_PERIOD, subscription_id)
 }
 
-fn idem_key(subscription_id: u32) -> (Symbol, u32) {
+pub(crate) fn idem_key(subscription_id: u32) -> (Symbol, u32) {
     (KEY_IDEM, subscription_id)
 }
 
+pub(crate) fn usage_idem_key(subscription_id: u32) -> (Symbol, u32) {
+    (KEY_USAGE_IDEM, subscription_id)
+}
+
+pub(crate) fn usage_seq_key(subscription_id: u32) -> (Symbol, u32) {
+    (KEY_USAGE_SEQ, subscription_id)
+}
+
+pub(crate) fn dunning_key(subscription_id: u32) -> (Symbol, u32) {
+    (KEY_DUNNING, subscription_id)
+}
+
+/// Timestamp of the next dunning retry, or `None` once `schedule` is exhausted.
+pub(crate) fn next_retry_at(state: &DunningState, schedule: &Vec<u64>) -> Option<u64> {
+    schedule
+        .get(state.attempts)
+        .map(|offset| state.failed_at.saturating_add(offset))
+}
+
 /// Performs a single interval-based charge with optional replay protection.
 ///
 /// # Idempotency
 ///
 /// - If `idempotency_key` is `Some(k)` and we already processed this subscription with key `k`,
-///   returns `Ok(())` without changing state (idempotent success).
-/// - Otherwise we derive a period from `now / interval_seconds`. If this period was already
-///   charged, returns `Err(Error::Replay)`.
+///   returns `Ok(())` without changing state (idempotent success), whatever the
+///   subscription's status is now.
+/// - Otherwise we derive the current period from `now` and the subscription's
+///   `billing_anchor` and `interval`. If this period was already charged, returns
+///   `Err(Error::Replay)`.
+///
+/// # Billing periods
+///
+/// Period `k` starts `k` intervals after `billing_anchor` (see [`crate::calendar`] for
+/// calendar months and years). Period 0 is free: the deposit made at creation is plain
+/// prepaid balance that later charges spend, and the first regular charge is for period 1.
+/// The exceptions are `create_and_charge_subscription`, which bills period 0 up front, and
+/// a trial, after which period 0 (anchored at the trial end) is the first paid period. The same period index drives
+/// both eligibility and replay protection, and `last_payment_timestamp` is set to the start
+/// of the last charged period, so late charges never shift future billing dates.
+///
+/// # Missed intervals
+///
+/// The subscription's [`CatchUpPolicy`] decides how many missed periods one call charges.
+/// Under `Forgive`, only one is charged and the rest are skipped. Periods the balance could
+/// not cover under `ChargeAll`/`ChargeUpTo` stay due.
+///
+/// # Dunning
+///
+/// When a dunning schedule is configured (see [`crate::admin::get_dunning_schedule`]), a
+/// failed charge records a [`DunningState`] and an `InsufficientBalance` subscription may be
+/// retried at each scheduled offset; before that the retry fails with
+/// [`Error::IntervalNotElapsed`]. A successful retry reactivates the subscription. When the
+/// final retry fails, the subscription moves to `Expired`.
+///
+/// # Cancel at period end
+///
+/// If `cancel_at_period_end` is set, the attempt that would charge the next period instead
+/// moves the subscription to `Cancelled`, publishes `cancelled` and returns `Ok(())`.
+///
+/// A failed charge records its status and dunning updates before returning
+/// [`Error::InsufficientBalance`]; callers must not propagate that error out of the
+/// invocation, or the updates roll back. `batch_charge` absorbs it per item and
+/// `charge_subscription` reports it as a failed [`crate::types::BatchChargeResult`].
 ///
 /// # Storage
 ///
-/// Bounded: one `u64` (last charged period) and optionally one idempotency key per subscription.
+/// Bounded: one `u64` (last charged period), optionally one idempotency key and one
+/// dunning state per subscription.
 pub fn charge_one(
     env: &Env,
     subscription_id: u32,
     idempotency_key: Option<soroban_sdk::BytesN<32>>,
 ) -> Result<(), Error> {
+    require_not_paused(env, |p| p.charging)?;
     let mut sub = get_subscription(env, subscription_id)?;
 
-    if sub.status != SubscriptionStatus::Active {
-        return Err(Error::NotActive);
-    }
-
-    let now = env.ledger().timestamp();
-    let period_index = now / sub.interval_seconds;
-
-    // Idempotent return: same idempotency key already processed for this subscription
+    // Idempotent return: same idempotency key already processed for this subscription,
+    // even if it has since been paused, cancelled or drained.
     if let Some(ref k) = idempotency_key {
-        if let Some(stored) = env
-            .storage()
-            .instance()
-            .get::<_, soroban_sdk::BytesN<32>>(&idem_key(subscription_id))
+        if let Some(stored) =
+            get_persistent::<_, soroban_sdk::BytesN<32>>(env, &idem_key(subscription_id))
         {
             if stored == *k {
                 return Ok(());
@@ -66,45 +135,251 @@ pub fn charge_one(
         }
     }
 
+    let dunning = match sub.status {
+        SubscriptionStatus::Active => None,
+        SubscriptionStatus::InsufficientBalance => Some(
+            get_persistent::<_, DunningState>(env, &dunning_key(subscription_id))
+                .ok_or(Error::NotActive)?,
+        ),
+        _ => return Err(Error::NotActive),
+    };
+    let schedule = get_dunning_schedule(env);
+
+    let now = env.ledger().timestamp();
+    let current_period = period_at(&sub, now);
+
     // Replay: already charged for this billing period (derived key)
-    if let Some(stored_period) = env
-        .storage()
-        .instance()
-        .get::<_, u64>(&charged_period_key(subscription_id))
-    {
-        if period_index <= stored_period {
+    let last_charged = get_persistent::<_, u64>(env, &charged_period_key(subscription_id));
+    if let (Some(current), Some(last)) = (current_period, last_charged) {
+        if current <= last {
             return Err(Error::Replay);
         }
     }
 
-    let next_allowed = sub
-        .last_payment_timestamp
-        .checked_add(sub.interval_seconds)
-        .ok_or(Error::Overflow)?;
-    if now < next_allowed {
-        return Err(Error::IntervalNotElapsed);
+    if let Some(ref state) = dunning {
+        // A retry is already overdue for its interval; only the schedule gates it.
+        if let Some(retry_at) = next_retry_at(state, &schedule) {
+            if now < retry_at {
+                return Err(Error::IntervalNotElapsed);
+            }
+        }
+    } else if let Some(trial_end) = sub.trial_end {
+        // During a trial the first charge is due at `trial_end`, not one interval in.
+        if now < trial_end {
+            return Err(Error::TrialActive);
+        }
+    }
+
+    let next_period = match last_charged {
+        Some(last) => last.checked_add(1).ok_or(Error::Overflow)?,
+        None if sub.trial_end.is_some() => 0,
+        None => 1,
+    };
+    let current_period = match current_period {
+        Some(current) if current >= next_period => current,
+        _ => return Err(Error::IntervalNotElapsed),
+    };
+
+    if sub.cancel_at_period_end {
+        validate_status_transition(&sub.status, &SubscriptionStatus::Cancelled)?;
+        sub.status = SubscriptionStatus::Cancelled;
+        env.storage()
+            .persistent()
+            .remove(&dunning_key(subscription_id));
+        set_persistent(env, &subscription_id, &sub);
+        env.events()
+            .publish((Symbol::new(env, "cancelled"), subscription_id), ());
+        return Ok(());
     }
 
     if sub.prepaid_balance < sub.amount {
-        validate_status_transition(&sub.status, &SubscriptionStatus::InsufficientBalance)?;
-        sub.status = SubscriptionStatus::InsufficientBalance;
-        env.storage().instance().set(&subscription_id, &sub);
+        record_failed_charge(env, subscription_id, &mut sub, dunning, &schedule, now)?;
         return Err(Error::InsufficientBalance);
     }
 
+    if dunning.is_some() {
+        validate_status_transition(&sub.status, &SubscriptionStatus::Active)?;
+        sub.status = SubscriptionStatus::Active;
+        env.storage()
+            .persistent()
+            .remove(&dunning_key(subscription_id));
+    }
+
+    let (periods, charged_period) = catch_up_periods(&sub, next_period, current_period)?;
+    settle_charge(
+        env,
+        subscription_id,
+        sub,
+        periods,
+        charged_period,
+        idempotency_key,
+    )
+}
+
+/// Marks every period before the current one as settled, so a subscription
+/// resumed from `Paused` is billed only for the current period, whatever its
+/// [`CatchUpPolicy`]. Periods already settled are left alone.
+pub(crate) fn skip_paused_periods(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+) -> Result<(), Error> {
+    let Some(current) = period_at(sub, env.ledger().timestamp()) else {
+        return Ok(());
+    };
+    let next_period = match get_persistent::<_, u64>(env, &charged_period_key(subscription_id)) {
+        Some(last) => last.saturating_add(1),
+        None if sub.trial_end.is_some() => 0,
+        None => 1,
+    };
+    if current > next_period {
+        set_persistent(env, &charged_period_key(subscription_id), &(current - 1));
+        sub.last_payment_timestamp = period_start(sub, current - 1)?;
+    }
+    Ok(())
+}
+
+/// Index of the billing period containing `timestamp`, counted from the
+/// subscription's `billing_anchor`; `None` before the anchor.
+pub(crate) fn period_at(sub: &Subscription, timestamp: u64) -> Option<u64> {
+    sub.interval.period_at(sub.billing_anchor, timestamp)
+}
+
+/// Start timestamp of billing period `period`.
+pub(crate) fn period_start(sub: &Subscription, period: u64) -> Result<u64, Error> {
+    sub.interval
+        .period_start(sub.billing_anchor, period)
+        .ok_or(Error::Overflow)
+}
+
+/// Returns how many periods to charge and the last period they settle, per the
+/// subscription's [`CatchUpPolicy`]. Periods `next_period..=current_period` are
+/// due; the balance must cover at least one `amount`.
+fn catch_up_periods(
+    sub: &Subscription,
+    next_period: u64,
+    current_period: u64,
+) -> Result<(u32, u64), Error> {
+    let due = current_period - next_period + 1;
+    let cap = match sub.catch_up {
+        CatchUpPolicy::Forgive => 1,
+        CatchUpPolicy::ChargeAll => due,
+        CatchUpPolicy::ChargeUpTo(n) => due.min(u64::from(n.max(1))),
+    };
+    let affordable = u64::try_from(sub.prepaid_balance / sub.amount).unwrap_or(u64::MAX);
+    let periods = cap.min(affordable);
+
+    // Periods beyond the cap are forgiven; those the balance missed stay due.
+    let charged_period = if periods < cap {
+        next_period + periods - 1
+    } else {
+        current_period
+    };
+    let periods = u32::try_from(periods).map_err(|_| Error::Overflow)?;
+    Ok((periods, charged_period))
+}
+
+/// Records a failed interval charge: the first failure moves the subscription to
+/// `InsufficientBalance` and starts dunning; a failed retry advances the dunning
+/// state, expiring the subscription once the schedule is exhausted.
+fn record_failed_charge(
+    env: &Env,
+    subscription_id: u32,
+    sub: &mut Subscription,
+    dunning: Option<DunningState>,
+    schedule: &Vec<u64>,
+    now: u64,
+) -> Result<(), Error> {
+    let key = dunning_key(subscription_id);
+    let state = match dunning {
+        None => {
+            validate_status_transition(&sub.status, &SubscriptionStatus::InsufficientBalance)?;
+            sub.status = SubscriptionStatus::InsufficientBalance;
+            set_persistent(env, &subscription_id, sub);
+            if schedule.is_empty() {
+                env.storage().persistent().remove(&key);
+            } else {
+                set_persistent(
+                    env,
+                    &key,
+                    &DunningState {
+                        failed_at: now,
+                        attempts: 0,
+                    },
+                );
+            }
+            return Ok(());
+        }
+        Some(state) => DunningState {
+            failed_at: state.failed_at,
+            attempts: state.attempts.saturating_add(1),
+        },
+    };
+
+    if state.attempts < schedule.len() {
+        set_persistent(env, &key, &state);
+        return Ok(());
+    }
+
+    validate_status_transition(&sub.status, &SubscriptionStatus::Expired)?;
+    sub.status = SubscriptionStatus::Expired;
+    set_persistent(env, &subscription_id, sub);
+    env.storage().persistent().remove(&key);
+    env.events().publish(
+        (symbol_short!("expired"), subscription_id),
+        SubscriptionExpiredEvent {
+            subscription_id,
+            failed_at: state.failed_at,
+            attempts: state.attempts,
+        },
+    );
+    Ok(())
+}
+
+/// Charges the first interval of a subscription created in the same invocation,
+/// i.e. period 0, which starts at the billing anchor. Records it so a subsequent
+/// `charge_one` in the same period is rejected as a replay.
+///
+/// Fails with [`Error::InsufficientBalance`] without touching status; the caller
+/// is expected to abort the whole creation.
+pub(crate) fn charge_first_interval(env: &Env, subscription_id: u32) -> Result<(), Error> {
+    require_not_paused(env, |p| p.charging)?;
+    let sub = get_subscription(env, subscription_id)?;
+    if sub.prepaid_balance < sub.amount {
+        return Err(Error::InsufficientBalance);
+    }
+    settle_charge(env, subscription_id, sub, 1, 0, None)
+}
+
+/// Debits `periods` intervals ending with `charged_period`, credits the merchant,
+/// records replay keys and publishes the charge events. All eligibility checks
+/// must already have passed.
+fn settle_charge(
+    env: &Env,
+    subscription_id: u32,
+    mut sub: Subscription,
+    periods: u32,
+    charged_period: u64,
+    idempotency_key: Option<soroban_sdk::BytesN<32>>,
+) -> Result<(), Error> {
+    let total = sub
+        .amount
+        .checked_mul(i128::from(periods))
+        .ok_or(Error::Overflow)?;
     sub.prepaid_balance = sub
         .prepaid_balance
-        .checked_sub(sub.amount)
+        .checked_sub(total)
         .ok_or(Error::Overflow)?;
-    sub.last_payment_timestamp = now;
-    env.storage().instance().set(&subscription_id, &sub);
+    adjust_total_prepaid(env, -total)?;
+    sub.last_payment_timestamp = period_start(&sub, charged_period)?;
+    let converted_trial = sub.trial_end.take();
+    set_persistent(env, &subscription_id, &sub);
+    credit_merchant_balance(env, &sub.merchant, total)?;
 
     // Record charged period and optional idempotency key (bounded storage)
-    env.storage()
-        .instance()
-        .set(&charged_period_key(subscription_id), &period_index);
+    set_persistent(env, &charged_period_key(subscription_id), &charged_period);
     if let Some(k) = idempotency_key {
-        env.storage().instance().set(&idem_key(subscription_id), &k);
+        set_persistent(env, &idem_key(subscription_id), &k);
     }
 
     env.events().publish(
@@ -112,9 +387,19 @@ pub fn charge_one(
         SubscriptionChargedEvent {
             subscription_id,
             merchant: sub.merchant.clone(),
-            amount: sub.amount,
+            amount: total,
         },
     );
+    if let Some(trial_end) = converted_trial {
+        env.events().publish(
+            (Symbol::new(env, "trial_converted"), subscription_id),
+            TrialConvertedEvent {
+                subscription_id,
+                trial_end,
+                amount: sub.amount,
+            },
+        );
+    }
 
     Ok(())
 }
@@ -125,15 +410,40 @@ pub fn charge_one(
 /// * Subscription must exist (`NotFound`).
 /// * Subscription must be `Active` (`NotActive`).
 /// * `usage_enabled` must be `true` (`UsageNotEnabled`).
+/// * `report_seq` must exceed the last accepted report sequence (`Replay`).
 /// * `usage_amount` must be positive (`InvalidAmount`).
 /// * `prepaid_balance >= usage_amount` (`InsufficientPrepaidBalance`).
 ///
-/// On success the prepaid balance is reduced.  If the balance reaches zero
+/// On success the prepaid balance is reduced and the merchant's earned balance
+/// is credited by the same amount.  If the balance reaches zero
 /// the subscription transitions to `InsufficientBalance`, blocking further
 /// charges until the subscriber tops up.
-pub fn charge_usage_one(env: &Env, subscription_id: u32, usage_amount: i128) -> Result<(), Error> {
+///
+/// If `idempotency_key` is `Some(k)` and the last usage charge on this subscription
+/// used key `k`, returns `Ok(())` without debiting again, before any of the checks
+/// above. Only the most recent key is kept, so storage stays bounded.
+pub fn charge_usage_one(
+    env: &Env,
+    subscription_id: u32,
+    usage_amount: i128,
+    report_seq: u64,
+    idempotency_key: Option<soroban_sdk::BytesN<32>>,
+) -> Result<(), Error> {
+    require_not_paused(env, |p| p.charging)?;
     let mut sub = get_subscription(env, subscription_id)?;
 
+    // Checked before the status so a retried report that drained the balance
+    // still succeeds.
+    if let Some(ref k) = idempotency_key {
+        if let Some(stored) =
+            get_persistent::<_, soroban_sdk::BytesN<32>>(env, &usage_idem_key(subscription_id))
+        {
+            if stored == *k {
+                return Ok(());
+            }
+        }
+    }
+
     if sub.status != SubscriptionStatus::Active {
         return Err(Error::NotActive);
     }
@@ -142,6 +452,13 @@ pub fn charge_usage_one(env: &Env, subscription_id: u32, usage_amount: i128) ->
         return Err(Error::UsageNotEnabled);
     }
 
+    // Replay: this metering report (or a later one) was already applied.
+    if let Some(last_seq) = get_persistent::<_, u64>(env, &usage_seq_key(subscription_id)) {
+        if report_seq <= last_seq {
+            return Err(Error::Replay);
+        }
+    }
+
     if usage_amount <= 0 {
         return Err(Error::InvalidAmount);
     }
@@ -154,14 +471,24 @@ pub fn charge_usage_one(env: &Env, subscription_id: u32, usage_amount: i128) ->
         .prepaid_balance
         .checked_sub(usage_amount)
         .ok_or(Error::Overflow)?;
+    adjust_total_prepaid(env, -usage_amount)?;
 
     // If the vault is now empty, transition to InsufficientBalance so no
     // further charges (interval or usage) can proceed until top-up.
+    // This is not a failed charge, so no dunning state is recorded.
     if sub.prepaid_balance == 0 {
         validate_status_transition(&sub.status, &SubscriptionStatus::InsufficientBalance)?;
         sub.status = SubscriptionStatus::InsufficientBalance;
+        env.storage()
+            .persistent()
+            .remove(&dunning_key(subscription_id));
     }
 
-    env.storage().instance().set(&subscription_id, &sub);
+    set_persistent(env, &subscription_id, &sub);
+    credit_merchant_balance(env, &sub.merchant, usage_amount)?;
+    set_persistent(env, &usage_seq_key(subscription_id), &report_seq);
+    if let Some(k) = idempotency_key {
+        set_persistent(env, &usage_idem_key(subscription_id), &k);
+    }
     Ok(())
 }
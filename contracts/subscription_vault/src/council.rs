@@ -0,0 +1,171 @@
+//! Admin council: a set of signers plus a threshold that replaces the single admin.
+//!
+//! **PRs that only change council governance should edit this file only.**
+//!
+//! The admin installs a council with [`do_set_admin_council`]. From then on there is
+//! no single admin ([`crate::admin::require_admin`] fails with `CouncilRequired`):
+//! admin actions are proposed by a signer, collect approvals on-chain, and execute
+//! in the call that brings the approvals of current signers up to the threshold.
+//! Batch charging moves to the billing operator. The council hands control back by
+//! proposing a single admin, and stays in place until that admin calls `accept_admin`.
+
+use crate::admin::{
+    cancel_config_change, recover_funds, require_admin, schedule_config_change, set_pause_flags,
+    store_pending_admin,
+};
+use crate::storage::{get_persistent, set_persistent};
+use crate::types::{AdminCouncil, CouncilAction, CouncilProposal, DataKey, Error};
+use soroban_sdk::{Address, Env, Symbol, Vec};
+
+pub fn get_admin_council(env: &Env) -> Option<AdminCouncil> {
+    env.storage().instance().get(&Symbol::new(env, "council"))
+}
+
+pub fn get_council_proposal(env: &Env, proposal_id: u32) -> Result<CouncilProposal, Error> {
+    get_persistent(env, &DataKey::CouncilProposal(proposal_id)).ok_or(Error::NotFound)
+}
+
+/// Rejects a council with no signers, duplicate signers, or a threshold that is
+/// zero or above the number of signers.
+fn validate_council(council: &AdminCouncil) -> Result<(), Error> {
+    if council.threshold == 0 || council.threshold > council.signers.len() {
+        return Err(Error::InvalidCouncil);
+    }
+    for (i, signer) in council.signers.iter().enumerate() {
+        if council.signers.first_index_of(&signer) != Some(i as u32) {
+            return Err(Error::InvalidCouncil);
+        }
+    }
+    Ok(())
+}
+
+fn store_council(env: &Env, council: AdminCouncil) -> Result<(), Error> {
+    validate_council(&council)?;
+    env.storage()
+        .instance()
+        .set(&Symbol::new(env, "council"), &council);
+    env.events()
+        .publish((Symbol::new(env, "council_set"),), council);
+    Ok(())
+}
+
+/// Hands admin control to `council`, removing the single admin and any pending
+/// admin proposal. Only callable by admin.
+pub fn do_set_admin_council(env: &Env, admin: Address, council: AdminCouncil) -> Result<(), Error> {
+    admin.require_auth();
+    let stored = require_admin(env)?;
+    if admin != stored {
+        return Err(Error::Unauthorized);
+    }
+    store_council(env, council)?;
+    env.storage().instance().remove(&Symbol::new(env, "admin"));
+    env.storage()
+        .instance()
+        .remove(&Symbol::new(env, "pending_admin"));
+    Ok(())
+}
+
+/// Checks that `signer` authorized the call and sits on the current council.
+fn require_signer(env: &Env, signer: &Address) -> Result<AdminCouncil, Error> {
+    signer.require_auth();
+    let council = get_admin_council(env).ok_or(Error::NotFound)?;
+    if !council.signers.contains(signer) {
+        return Err(Error::Unauthorized);
+    }
+    Ok(council)
+}
+
+/// Proposes `action`, counting the proposer's approval. Returns the proposal id.
+/// With a threshold of one the action executes immediately.
+pub fn do_propose_council_action(
+    env: &Env,
+    signer: Address,
+    action: CouncilAction,
+) -> Result<u32, Error> {
+    let council = require_signer(env, &signer)?;
+    if let CouncilAction::SetCouncil(ref next) = action {
+        validate_council(next)?;
+    }
+
+    let key = Symbol::new(env, "next_council_id");
+    let id: u32 = env.storage().instance().get(&key).unwrap_or(0);
+    env.storage().instance().set(&key, &(id + 1));
+
+    let proposal = CouncilProposal {
+        action: action.clone(),
+        proposer: signer.clone(),
+        approvals: Vec::from_array(env, [signer.clone()]),
+        executed: false,
+    };
+    env.events()
+        .publish((Symbol::new(env, "council_proposed"), id), (signer, action));
+    execute_if_approved(env, id, proposal, &council)?;
+    Ok(id)
+}
+
+/// Adds `signer`'s approval to a pending proposal, executing it once the
+/// threshold is met. If execution fails the approval is not recorded.
+pub fn do_approve_council_action(
+    env: &Env,
+    signer: Address,
+    proposal_id: u32,
+) -> Result<(), Error> {
+    let council = require_signer(env, &signer)?;
+    let mut proposal = get_council_proposal(env, proposal_id)?;
+    if proposal.executed {
+        return Err(Error::ProposalAlreadyExecuted);
+    }
+    if proposal.approvals.contains(&signer) {
+        return Err(Error::AlreadyApproved);
+    }
+    proposal.approvals.push_back(signer.clone());
+    env.events()
+        .publish((Symbol::new(env, "council_approved"), proposal_id), signer);
+    execute_if_approved(env, proposal_id, proposal, &council)
+}
+
+/// Stores the proposal, executing it first if approvals from current signers
+/// meet the threshold (approvals from removed signers no longer count).
+fn execute_if_approved(
+    env: &Env,
+    proposal_id: u32,
+    mut proposal: CouncilProposal,
+    council: &AdminCouncil,
+) -> Result<(), Error> {
+    let approvals = proposal
+        .approvals
+        .iter()
+        .filter(|a| council.signers.contains(a))
+        .count() as u32;
+    if approvals >= council.threshold {
+        proposal.executed = true;
+        execute_action(env, proposal.action.clone())?;
+        env.events()
+            .publish((Symbol::new(env, "council_executed"), proposal_id), ());
+    }
+    set_persistent(env, &DataKey::CouncilProposal(proposal_id), &proposal);
+    Ok(())
+}
+
+fn execute_action(env: &Env, action: CouncilAction) -> Result<(), Error> {
+    match action {
+        CouncilAction::RecoverFunds(recipient, amount, reason) => recover_funds(
+            env,
+            env.current_contract_address(),
+            recipient,
+            amount,
+            reason,
+        ),
+        CouncilAction::SetAdmin(new_admin) => {
+            store_pending_admin(env, env.current_contract_address(), new_admin, None);
+            Ok(())
+        }
+        CouncilAction::SetCouncil(council) => store_council(env, council),
+        CouncilAction::Config(change) => schedule_config_change(env, change),
+        CouncilAction::CancelConfigChange(id) => cancel_config_change(env, id),
+        CouncilAction::SetPauseFlags(flags) => {
+            set_pause_flags(env, flags);
+            Ok(())
+        }
+    }
+}
@@ -2,10 +2,14 @@
 
 // ── Modules ──────────────────────────────────────────────────────────────────
 mod admin;
+mod calendar;
 mod charge_core;
+mod council;
 mod merchant;
+mod plans;
 mod queries;
 mod state_machine;
+mod storage;
 mod subscription;
 pub mod types;
 
@@ -14,7 +18,7 @@ pub use state_machine::{can_transition, get_allowed_transitions, validate_status
 pub use types::*;
 
 pub use queries::compute_next_charge_info;
-use soroban_sdk::{contract, contractimpl, Address, Env, Vec};
+use soroban_sdk::{contract, contractimpl, Address, BytesN, Env, Vec};
 
 // ── Contract ─────────────────────────────────────────────────────────────────
 
@@ -26,6 +30,7 @@ impl SubscriptionVault {
     // ── Admin / Config ───────────────────────────────────────────────────
 
     /// Initialize the contract: set token address, admin, and minimum top-up.
+    /// Can only be called once (`AlreadyInitialized`).
     pub fn init(env: Env, token: Address, admin: Address, min_topup: i128) -> Result<(), Error> {
         admin::do_init(&env, token, admin, min_topup)
     }
@@ -40,27 +45,187 @@ impl SubscriptionVault {
         admin::get_min_topup(&env)
     }
 
+    /// Register the billing operator allowed to call `charge_subscription` and
+    /// `charge_usage`. Only callable by admin.
+    pub fn set_billing_operator(env: Env, admin: Address, operator: Address) -> Result<(), Error> {
+        admin::do_set_billing_operator(&env, admin, operator)
+    }
+
+    /// Get the registered billing operator.
+    pub fn get_billing_operator(env: Env) -> Result<Address, Error> {
+        admin::get_billing_operator(&env)
+    }
+
+    /// Set which parties (subscriber, merchant, admin) may pause, resume or cancel
+    /// subscriptions. Only callable by admin.
+    pub fn set_action_policy(
+        env: Env,
+        admin: Address,
+        action: LifecycleAction,
+        policy: ActionPolicy,
+    ) -> Result<(), Error> {
+        admin::do_set_action_policy(&env, admin, action, policy)
+    }
+
+    /// Get the authorization policy for a lifecycle action.
+    pub fn get_action_policy(env: Env, action: LifecycleAction) -> ActionPolicy {
+        admin::get_action_policy(&env, action)
+    }
+
+    /// Set the dunning retry schedule: offsets (seconds after the first failed
+    /// charge) at which `InsufficientBalance` subscriptions are retried before
+    /// they expire. Offsets must be strictly increasing; empty disables dunning.
+    /// Only callable by admin.
+    pub fn set_dunning_schedule(
+        env: Env,
+        admin: Address,
+        retry_offsets: Vec<u64>,
+    ) -> Result<(), Error> {
+        admin::do_set_dunning_schedule(&env, admin, retry_offsets)
+    }
+
+    /// Get the dunning retry schedule (empty if dunning is disabled).
+    pub fn get_dunning_schedule(env: Env) -> Vec<u64> {
+        admin::get_dunning_schedule(&env)
+    }
+
+    /// Queue a config change to take effect at `eta` (a ledger timestamp at least
+    /// the timelock delay from now). Returns the change id. Once a non-zero delay
+    /// is in effect, the instant setters fail with `TimelockRequired`. Only
+    /// callable by admin.
+    pub fn queue_config_change(
+        env: Env,
+        admin: Address,
+        change: ConfigChange,
+        eta: u64,
+    ) -> Result<u32, Error> {
+        admin::do_queue_config_change(&env, admin, change, eta)
+    }
+
+    /// Apply a queued config change once its `eta` has passed. Callable by anyone.
+    pub fn execute_config_change(env: Env, change_id: u32) -> Result<(), Error> {
+        admin::do_execute_config_change(&env, change_id)
+    }
+
+    /// Drop a queued config change. Only callable by admin.
+    pub fn cancel_config_change(env: Env, admin: Address, change_id: u32) -> Result<(), Error> {
+        admin::do_cancel_config_change(&env, admin, change_id)
+    }
+
+    /// List all queued config changes.
+    pub fn get_queued_config_changes(env: Env) -> Vec<QueuedConfigChange> {
+        admin::get_queued_config_changes(&env)
+    }
+
+    /// Get the timelock delay in seconds (0: config setters apply instantly).
+    pub fn get_timelock_delay(env: Env) -> u64 {
+        admin::get_timelock_delay(&env)
+    }
+
+    /// Emergency stop: pause charging, deposits and/or merchant withdrawals
+    /// contract-wide. Subscriber refunds stay available. Only callable by admin.
+    pub fn set_pause_flags(env: Env, admin: Address, flags: PauseFlags) -> Result<(), Error> {
+        admin::do_set_pause_flags(&env, admin, flags)
+    }
+
+    /// Get the emergency pause flags.
+    pub fn get_pause_flags(env: Env) -> PauseFlags {
+        admin::get_pause_flags(&env)
+    }
+
+    /// Set the bounds on amount and interval enforced when subscriptions and
+    /// plans are created. Only callable by admin.
+    pub fn set_subscription_limits(
+        env: Env,
+        admin: Address,
+        limits: SubscriptionLimits,
+    ) -> Result<(), Error> {
+        admin::do_set_subscription_limits(&env, admin, limits)
+    }
+
+    /// Get the subscription limits (defaults allow any positive amount and
+    /// non-zero interval).
+    pub fn get_subscription_limits(env: Env) -> SubscriptionLimits {
+        admin::get_subscription_limits(&env)
+    }
+
     /// Get the current admin address.
     pub fn get_admin(env: Env) -> Result<Address, Error> {
         admin::do_get_admin(&env)
     }
 
-    /// Rotate admin to a new address. Only callable by current admin.
-    ///
-    /// # Security
-    ///
-    /// - Immediate effect — old admin loses access instantly.
-    /// - Irreversible without the new admin's cooperation.
-    /// - Emits an `admin_rotation` event for audit trail.
-    pub fn rotate_admin(env: Env, current_admin: Address, new_admin: Address) -> Result<(), Error> {
-        admin::do_rotate_admin(&env, current_admin, new_admin)
+    /// Replace the single admin with a council of signers and a threshold. Admin
+    /// actions then go through `propose_council_action`, and batch charging
+    /// moves to the billing operator. Only callable by admin.
+    pub fn set_admin_council(env: Env, admin: Address, council: AdminCouncil) -> Result<(), Error> {
+        council::do_set_admin_council(&env, admin, council)
+    }
+
+    /// Propose a council action, counting the proposer's approval. Returns the
+    /// proposal id. Auth: a council signer.
+    pub fn propose_council_action(
+        env: Env,
+        signer: Address,
+        action: CouncilAction,
+    ) -> Result<u32, Error> {
+        council::do_propose_council_action(&env, signer, action)
+    }
+
+    /// Approve a council proposal; it executes once approvals reach the
+    /// threshold. Auth: a council signer.
+    pub fn approve_council_action(
+        env: Env,
+        signer: Address,
+        proposal_id: u32,
+    ) -> Result<(), Error> {
+        council::do_approve_council_action(&env, signer, proposal_id)
+    }
+
+    /// Get the admin council, if one is installed.
+    pub fn get_admin_council(env: Env) -> Option<AdminCouncil> {
+        council::get_admin_council(&env)
+    }
+
+    /// Get a council proposal by id.
+    pub fn get_council_proposal(env: Env, proposal_id: u32) -> Result<CouncilProposal, Error> {
+        council::get_council_proposal(&env, proposal_id)
+    }
+
+    /// Propose `new_admin` as the next admin, optionally only until `expires_at`.
+    /// The transfer completes when the new admin calls `accept_admin`, so a
+    /// mistyped address never takes over. Only callable by current admin.
+    pub fn propose_admin(
+        env: Env,
+        current_admin: Address,
+        new_admin: Address,
+        expires_at: Option<u64>,
+    ) -> Result<(), Error> {
+        admin::do_propose_admin(&env, current_admin, new_admin, expires_at)
+    }
+
+    /// Accept a pending admin proposal. Must be authorized by the proposed admin
+    /// before the proposal expires. Accepting a council's proposal dissolves the council.
+    pub fn accept_admin(env: Env, new_admin: Address) -> Result<(), Error> {
+        admin::do_accept_admin(&env, new_admin)
+    }
+
+    /// Withdraw the pending admin proposal. Only callable by current admin.
+    pub fn cancel_admin_proposal(env: Env, current_admin: Address) -> Result<(), Error> {
+        admin::do_cancel_admin_proposal(&env, current_admin)
+    }
+
+    /// Get the pending admin proposal, if any.
+    pub fn get_pending_admin(env: Env) -> Option<PendingAdmin> {
+        admin::get_pending_admin(&env)
     }
 
     /// **ADMIN ONLY**: Recover stranded funds from the contract.
     ///
     /// Tightly-scoped mechanism for recovering funds that have become
-    /// inaccessible through normal operations. Each recovery emits a
-    /// `RecoveryEvent` with full audit details.
+    /// inaccessible through normal operations. Transfers vault tokens to
+    /// `recipient`, but never more than `get_recoverable_surplus`, so subscriber
+    /// and merchant balances cannot be touched (`ExceedsSurplus`). Each recovery
+    /// emits a `RecoveryEvent` with full audit details.
     pub fn recover_stranded_funds(
         env: Env,
         admin: Address,
@@ -71,6 +236,12 @@ impl SubscriptionVault {
         admin::do_recover_stranded_funds(&env, admin, recipient, amount, reason)
     }
 
+    /// Vault tokens not owed to subscribers or merchants, i.e. the most
+    /// `recover_stranded_funds` can transfer out.
+    pub fn get_recoverable_surplus(env: Env) -> Result<i128, Error> {
+        admin::get_recoverable_surplus(&env)
+    }
+
     /// Charge a batch of subscriptions in one transaction. Admin only.
     ///
     /// Returns a per-subscription result vector so callers can identify
@@ -82,9 +253,23 @@ impl SubscriptionVault {
         admin::do_batch_charge(&env, &subscription_ids)
     }
 
+    /// Charge a batch of `(subscription_id, idempotency_key)` pairs. Admin only.
+    ///
+    /// Retrying a batch with the same keys reports success for charges already
+    /// applied without debiting them again.
+    pub fn batch_charge_idempotent(
+        env: Env,
+        charges: Vec<(u32, BytesN<32>)>,
+    ) -> Result<Vec<BatchChargeResult>, Error> {
+        admin::do_batch_charge_idempotent(&env, &charges)
+    }
+
     // ── Subscription lifecycle ───────────────────────────────────────────
 
-    /// Create a new subscription. Caller deposits initial USDC; contract stores agreement.
+    /// Create a new subscription billed every `interval_seconds`. Caller prepays the
+    /// first interval (`amount`) into the vault via the configured token; contract
+    /// stores agreement. Calendar intervals are available through plans. Auth:
+    /// subscriber and merchant, since the terms are not taken from a plan.
     pub fn create_subscription(
         env: Env,
         subscriber: Address,
@@ -103,9 +288,41 @@ impl SubscriptionVault {
         )
     }
 
+    /// Create a subscription billed in advance: `initial_deposit` is pulled into the
+    /// vault and the first interval is charged immediately. Fails atomically with
+    /// `InsufficientBalance` if the deposit does not cover `amount`. Auth: subscriber
+    /// and merchant.
+    pub fn create_and_charge_subscription(
+        env: Env,
+        subscriber: Address,
+        merchant: Address,
+        amount: i128,
+        interval_seconds: u64,
+        usage_enabled: bool,
+        initial_deposit: i128,
+    ) -> Result<u32, Error> {
+        subscription::do_create_and_charge_subscription(
+            &env,
+            subscriber,
+            merchant,
+            amount,
+            interval_seconds,
+            usage_enabled,
+            initial_deposit,
+        )
+    }
+
+    /// Subscribe to a merchant-published plan. The plan's price, interval and usage
+    /// mode are copied into the new subscription; the first interval is prepaid.
+    pub fn subscribe_to_plan(env: Env, subscriber: Address, plan_id: u32) -> Result<u32, Error> {
+        plans::do_subscribe_to_plan(&env, subscriber, plan_id)
+    }
+
     /// Subscriber deposits more USDC into their prepaid vault.
     ///
-    /// Rejects deposits below the configured minimum threshold.
+    /// Tokens are transferred from `subscriber` to the contract. Rejects deposits
+    /// below the configured minimum threshold. A subscription in `InsufficientBalance`
+    /// is reactivated once the balance covers one interval.
     pub fn deposit_funds(
         env: Env,
         subscription_id: u32,
@@ -115,8 +332,46 @@ impl SubscriptionVault {
         subscription::do_deposit_funds(&env, subscription_id, subscriber, amount)
     }
 
+    /// Set how missed intervals are charged (`Forgive`, `ChargeAll`, `ChargeUpTo(n)`).
+    /// Auth: subscriber and merchant.
+    pub fn set_catch_up_policy(
+        env: Env,
+        subscription_id: u32,
+        policy: CatchUpPolicy,
+    ) -> Result<(), Error> {
+        subscription::do_set_catch_up_policy(&env, subscription_id, policy)
+    }
+
+    /// Change a subscription's price, directly or by moving to another plan of the
+    /// same merchant and interval. If the current period is already paid, the
+    /// difference for its unused part is charged or refunded at once. Auth:
+    /// subscriber and merchant for a price increase; otherwise subscriber (plan)
+    /// or merchant (amount).
+    pub fn change_subscription_terms(
+        env: Env,
+        subscription_id: u32,
+        change: TermsChange,
+    ) -> Result<(), Error> {
+        subscription::do_change_subscription_terms(&env, subscription_id, change)
+    }
+
+    /// Deposit like `deposit_funds`, then immediately charge the interval that is
+    /// due (e.g. the one missed while underfunded), if any. While charging is
+    /// paused only the deposit is made. Auth: subscriber.
+    pub fn deposit_and_settle(
+        env: Env,
+        subscription_id: u32,
+        subscriber: Address,
+        amount: i128,
+    ) -> Result<(), Error> {
+        subscription::do_deposit_and_settle(&env, subscription_id, subscriber, amount)
+    }
+
     /// Cancel the subscription. Allowed from Active, Paused, or InsufficientBalance.
     /// Transitions to the terminal `Cancelled` state.
+    ///
+    /// `authorizer` must be permitted by the `Cancel` action policy (default:
+    /// subscriber or merchant), otherwise `Unauthorized`.
     pub fn cancel_subscription(
         env: Env,
         subscription_id: u32,
@@ -125,7 +380,37 @@ impl SubscriptionVault {
         subscription::do_cancel_subscription(&env, subscription_id, authorizer)
     }
 
+    /// Cancel at the end of the current period: the subscription stays `Active`
+    /// until its next charge is due, and that charge attempt cancels it instead.
+    /// Allowed from Active; authorized by the `Cancel` action policy.
+    pub fn cancel_at_period_end(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+    ) -> Result<(), Error> {
+        subscription::do_cancel_at_period_end(&env, subscription_id, authorizer)
+    }
+
+    /// Withdraw a scheduled cancel-at-period-end before the period ends
+    /// (`PeriodEnded` after). Authorized by the `Cancel` action policy.
+    pub fn undo_cancel_at_period_end(
+        env: Env,
+        subscription_id: u32,
+        authorizer: Address,
+    ) -> Result<(), Error> {
+        subscription::do_undo_cancel_at_period_end(&env, subscription_id, authorizer)
+    }
+
+    /// Subscriber withdraws the unused prepaid balance of a `Cancelled` subscription.
+    ///
+    /// Transfers the full remaining `prepaid_balance` back to the subscriber and
+    /// zeroes it. Fails with `NotCancelled` if the subscription is not cancelled.
+    pub fn withdraw_subscriber_funds(env: Env, subscription_id: u32) -> Result<(), Error> {
+        subscription::do_withdraw_subscriber_funds(&env, subscription_id)
+    }
+
     /// Pause subscription (no charges until resumed). Allowed from Active.
+    /// `authorizer` must be permitted by the `Pause` action policy.
     pub fn pause_subscription(
         env: Env,
         subscription_id: u32,
@@ -135,6 +420,7 @@ impl SubscriptionVault {
     }
 
     /// Resume a subscription to Active. Allowed from Paused or InsufficientBalance.
+    /// `authorizer` must be permitted by the `Resume` action policy.
     pub fn resume_subscription(
         env: Env,
         subscription_id: u32,
@@ -143,31 +429,57 @@ impl SubscriptionVault {
         subscription::do_resume_subscription(&env, subscription_id, authorizer)
     }
 
+    /// Extend the storage TTL of a subscription and its related entries so it is
+    /// not archived. Callable by anyone; the caller pays the rent. Keepers must
+    /// call this for subscriptions billed less often than every ~120 days.
+    pub fn extend_subscription_ttl(env: Env, subscription_id: u32) -> Result<(), Error> {
+        subscription::do_extend_subscription_ttl(&env, subscription_id)
+    }
+
     // ── Charging ─────────────────────────────────────────────────────────
 
     /// Billing engine calls this to charge one interval.
     ///
-    /// Enforces strict interval timing and replay protection.
-    pub fn charge_subscription(env: Env, subscription_id: u32) -> Result<(), Error> {
-        charge_core::charge_one(&env, subscription_id, None)
+    /// Requires auth from the billing operator (or the admin if no operator is set).
+    /// Enforces strict interval timing and replay protection. A charge the prepaid
+    /// balance cannot cover returns `success: false` with `InsufficientBalance`'s code
+    /// and still moves the subscription into `InsufficientBalance` or dunning.
+    pub fn charge_subscription(env: Env, subscription_id: u32) -> Result<BatchChargeResult, Error> {
+        subscription::do_charge_subscription(&env, subscription_id, None)
+    }
+
+    /// Same as `charge_subscription`, keyed for safe retries by the billing engine.
+    ///
+    /// A repeated call with the same `idempotency_key` for this subscription returns
+    /// success without charging again.
+    pub fn charge_subscription_idempotent(
+        env: Env,
+        subscription_id: u32,
+        idempotency_key: BytesN<32>,
+    ) -> Result<BatchChargeResult, Error> {
+        subscription::do_charge_subscription(&env, subscription_id, Some(idempotency_key))
     }
 
     /// Charge a metered usage amount against the subscription's prepaid balance.
     ///
     /// Designed for integration with an **off-chain usage metering service**:
     /// the service measures consumption, then calls this entrypoint with the
-    /// computed `usage_amount` to debit the subscriber's vault.
+    /// computed `usage_amount` to debit the subscriber's vault. Each metering
+    /// report carries a `report_seq` that must increase strictly per subscription.
     ///
     /// # Requirements
     ///
+    /// * Auth from the billing operator (or the admin if no operator is set).
     /// * The subscription must be `Active`.
     /// * `usage_enabled` must be `true` on the subscription.
+    /// * `report_seq` must be greater than the last accepted report sequence.
     /// * `usage_amount` must be positive (`> 0`).
     /// * `prepaid_balance` must be >= `usage_amount`.
     ///
     /// # Behaviour
     ///
-    /// On success, `prepaid_balance` is reduced by `usage_amount`.  If the
+    /// On success, `prepaid_balance` is reduced by `usage_amount` and the
+    /// merchant's earned balance is credited by the same amount.  If the
     /// debit drains the balance to zero the subscription transitions to
     /// `InsufficientBalance` status, signalling that no further charges
     /// (interval or usage) can proceed until the subscriber tops up.
@@ -179,19 +491,104 @@ impl SubscriptionVault {
     /// | `NotFound` | Subscription ID does not exist. |
     /// | `NotActive` | Subscription is not `Active`. |
     /// | `UsageNotEnabled` | `usage_enabled` is `false`. |
+    /// | `Replay` | `report_seq` was already used or is older than the last report. |
     /// | `InvalidAmount` | `usage_amount` is zero or negative. |
     /// | `InsufficientPrepaidBalance` | Prepaid balance cannot cover the debit. |
-    pub fn charge_usage(env: Env, subscription_id: u32, usage_amount: i128) -> Result<(), Error> {
-        charge_core::charge_usage_one(&env, subscription_id, usage_amount)
+    pub fn charge_usage(
+        env: Env,
+        subscription_id: u32,
+        usage_amount: i128,
+        report_seq: u64,
+    ) -> Result<(), Error> {
+        subscription::do_charge_usage(&env, subscription_id, usage_amount, report_seq, None)
+    }
+
+    /// Same as `charge_usage`, keyed by the metering report so network retries
+    /// never double-debit.
+    ///
+    /// If the last usage charge on this subscription used `idempotency_key`, returns
+    /// `Ok(())` without debiting again (instead of the `Replay` error a reused
+    /// `report_seq` would otherwise produce).
+    pub fn charge_usage_idempotent(
+        env: Env,
+        subscription_id: u32,
+        usage_amount: i128,
+        report_seq: u64,
+        idempotency_key: BytesN<32>,
+    ) -> Result<(), Error> {
+        subscription::do_charge_usage(
+            &env,
+            subscription_id,
+            usage_amount,
+            report_seq,
+            Some(idempotency_key),
+        )
     }
 
     // ── Merchant ─────────────────────────────────────────────────────────
 
     /// Merchant withdraws accumulated USDC to their wallet.
+    ///
+    /// Debits the merchant's earned balance and transfers `amount` tokens from the
+    /// vault. Fails with `InsufficientMerchantBalance` if `amount` exceeds it.
     pub fn withdraw_merchant_funds(env: Env, merchant: Address, amount: i128) -> Result<(), Error> {
         merchant::withdraw_merchant_funds(&env, merchant, amount)
     }
 
+    // ── Plans ────────────────────────────────────────────────────────────
+
+    /// Merchant publishes a plan billed every `interval` (seconds, days, calendar
+    /// months or years). `token` must be the vault's token; `metadata_hash`
+    /// commits to off-chain plan details. A non-zero `trial_seconds` gives new
+    /// subscribers a free trial before the first charge.
+    #[allow(clippy::too_many_arguments)]
+    pub fn create_plan(
+        env: Env,
+        merchant: Address,
+        amount: i128,
+        interval: BillingInterval,
+        usage_enabled: bool,
+        trial_seconds: u64,
+        token: Address,
+        metadata_hash: BytesN<32>,
+    ) -> Result<u32, Error> {
+        plans::do_create_plan(
+            &env,
+            merchant,
+            amount,
+            interval,
+            usage_enabled,
+            trial_seconds,
+            token,
+            metadata_hash,
+        )
+    }
+
+    /// Read plan by id.
+    pub fn get_plan(env: Env, plan_id: u32) -> Result<Plan, Error> {
+        plans::get_plan(&env, plan_id)
+    }
+
+    /// Return a merchant's plan catalog, paginated.
+    pub fn get_plans_by_merchant(env: Env, merchant: Address, start: u32, limit: u32) -> Vec<Plan> {
+        plans::get_plans_by_merchant(&env, merchant, start, limit)
+    }
+
+    /// Set the catch-up policy copied into future subscriptions on a plan.
+    /// Existing subscriptions keep theirs. Auth: plan merchant.
+    pub fn set_plan_catch_up_policy(
+        env: Env,
+        plan_id: u32,
+        policy: CatchUpPolicy,
+    ) -> Result<(), Error> {
+        plans::do_set_plan_catch_up_policy(&env, plan_id, policy)
+    }
+
+    /// Return the number of plans a merchant has published.
+    pub fn get_merchant_plan_count(env: Env, merchant: Address) -> u32 {
+        plans::get_merchant_plan_count(&env, merchant)
+    }
+
     // ── Queries ──────────────────────────────────────────────────────────
 
     /// Read subscription by id.
@@ -210,8 +607,7 @@ impl SubscriptionVault {
 
     /// Get estimated next charge info (timestamp + whether charge is expected).
     pub fn get_next_charge_info(env: Env, subscription_id: u32) -> Result<NextChargeInfo, Error> {
-        let sub = queries::get_subscription(&env, subscription_id)?;
-        Ok(compute_next_charge_info(&sub))
+        queries::get_next_charge_info(&env, subscription_id)
     }
 
     /// Return subscriptions for a merchant, paginated.
@@ -228,6 +624,28 @@ impl SubscriptionVault {
     pub fn get_merchant_subscription_count(env: Env, merchant: Address) -> u32 {
         queries::get_merchant_subscription_count(&env, merchant)
     }
+
+    /// Return the merchant's accrued earnings from successful charges, net of withdrawals
+    /// and downgrade refunds.
+    pub fn get_merchant_balance(env: Env, merchant: Address) -> i128 {
+        queries::get_merchant_balance(&env, merchant)
+    }
+}
+
+/// Test-only entrypoints, compiled with `cfg(test)` or the `testutils` feature.
+#[cfg(any(test, feature = "testutils"))]
+#[contractimpl]
+impl SubscriptionVault {
+    /// Rotate admin to a new address in one step. Only callable by current admin.
+    ///
+    /// # Security
+    ///
+    /// - Immediate effect — old admin loses access instantly.
+    /// - Irreversible without the new admin's cooperation.
+    /// - Emits an `admin_rotation` event for audit trail.
+    pub fn rotate_admin(env: Env, current_admin: Address, new_admin: Address) -> Result<(), Error> {
+        admin::do_rotate_admin(&env, current_admin, new_admin)
+    }
 }
 
 #[cfg(test)]
@@ -1,13 +1,72 @@
-//! Merchant entrypoints: withdraw_merchant_funds.
+//! Merchant entrypoints: withdraw_merchant_funds, plus the merchant earnings ledger.
 //!
 //! **PRs that only change merchant payouts should edit this file only.**
 
-use crate::types::Error;
-use soroban_sdk::{Address, Env, Symbol};
+use crate::admin::adjust_total_merchant;
+use crate::queries::get_merchant_balance;
+use crate::storage::set_persistent;
+use crate::types::{DataKey, Error, MerchantWithdrawalEvent};
+use soroban_sdk::{token, Address, Env, Symbol};
 
-pub fn withdraw_merchant_funds(_env: &Env, merchant: Address, _amount: i128) -> Result<(), Error> {
+/// Credits `amount` to the merchant's earned balance. Called by charge logic
+/// in the same invocation as the subscriber debit, so both commit or neither does.
+pub fn credit_merchant_balance(env: &Env, merchant: &Address, amount: i128) -> Result<(), Error> {
+    let balance = get_merchant_balance(env, merchant.clone())
+        .checked_add(amount)
+        .ok_or(Error::Overflow)?;
+    set_persistent(env, &DataKey::MerchantBalance(merchant.clone()), &balance);
+    adjust_total_merchant(env, amount)
+}
+
+/// Debits `amount` from the merchant's earned balance, e.g. to refund a
+/// prorated downgrade. Fails with [`Error::InsufficientMerchantBalance`] if the
+/// merchant has already withdrawn it.
+pub fn debit_merchant_balance(env: &Env, merchant: &Address, amount: i128) -> Result<(), Error> {
+    let balance = get_merchant_balance(env, merchant.clone());
+    if amount > balance {
+        return Err(Error::InsufficientMerchantBalance);
+    }
+    set_persistent(
+        env,
+        &DataKey::MerchantBalance(merchant.clone()),
+        &(balance - amount),
+    );
+    adjust_total_merchant(env, -amount)
+}
+
+/// Pays out `amount` of the merchant's accrued balance in tokens.
+///
+/// The ledger is debited before the transfer; an amount above the accrued
+/// balance is rejected with [`Error::InsufficientMerchantBalance`].
+pub fn withdraw_merchant_funds(env: &Env, merchant: Address, amount: i128) -> Result<(), Error> {
     merchant.require_auth();
-    _env.events()
-        .publish((Symbol::new(_env, "withdrawn"), merchant.clone()), _amount);
+    crate::admin::require_not_paused(env, |p| p.withdrawals)?;
+
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+
+    let balance = get_merchant_balance(env, merchant.clone());
+    if amount > balance {
+        return Err(Error::InsufficientMerchantBalance);
+    }
+    set_persistent(
+        env,
+        &DataKey::MerchantBalance(merchant.clone()),
+        &(balance - amount),
+    );
+    adjust_total_merchant(env, -amount)?;
+
+    let token_addr = crate::admin::get_token(env)?;
+    token::Client::new(env, &token_addr).transfer(
+        &env.current_contract_address(),
+        &merchant,
+        &amount,
+    );
+
+    env.events().publish(
+        (Symbol::new(env, "withdrawn"), merchant.clone()),
+        MerchantWithdrawalEvent { merchant, amount },
+    );
     Ok(())
 }
@@ -0,0 +1,125 @@
+//! Merchant plans: publish subscription terms on-chain and subscribe to them.
+//!
+//! **PRs that only change plan catalog or plan subscription behavior should edit this file only.**
+
+use crate::storage::{get_persistent, set_persistent};
+use crate::subscription::{open_subscription, validate_catch_up_policy};
+use crate::types::{BillingInterval, CatchUpPolicy, DataKey, Error, Plan};
+use soroban_sdk::{Address, BytesN, Env, Symbol, Vec};
+
+fn next_plan_id(env: &Env) -> u32 {
+    let key = Symbol::new(env, "next_plan_id");
+    let id: u32 = env.storage().instance().get(&key).unwrap_or(0);
+    env.storage().instance().set(&key, &(id + 1));
+    id
+}
+
+/// Publishes a new plan for `merchant`. Auth: merchant.
+#[allow(clippy::too_many_arguments)]
+pub fn do_create_plan(
+    env: &Env,
+    merchant: Address,
+    amount: i128,
+    interval: BillingInterval,
+    usage_enabled: bool,
+    trial_seconds: u64,
+    token: Address,
+    metadata_hash: BytesN<32>,
+) -> Result<u32, Error> {
+    merchant.require_auth();
+
+    crate::subscription::validate_terms(env, amount, &interval)?;
+    if token != crate::admin::get_token(env)? {
+        return Err(Error::InvalidToken);
+    }
+
+    let plan = Plan {
+        merchant: merchant.clone(),
+        amount,
+        interval,
+        usage_enabled,
+        trial_seconds,
+        catch_up: CatchUpPolicy::Forgive,
+        token,
+        metadata_hash,
+    };
+    let id = next_plan_id(env);
+    set_persistent(env, &DataKey::Plan(id), &plan);
+
+    // Maintain merchant → plan-ID index
+    let key = DataKey::MerchantPlans(merchant);
+    let mut ids: Vec<u32> = get_persistent(env, &key).unwrap_or(Vec::new(env));
+    ids.push_back(id);
+    set_persistent(env, &key, &ids);
+
+    env.events()
+        .publish((Symbol::new(env, "plan_created"), id), plan);
+    Ok(id)
+}
+
+pub fn get_plan(env: &Env, plan_id: u32) -> Result<Plan, Error> {
+    get_persistent(env, &DataKey::Plan(plan_id)).ok_or(Error::NotFound)
+}
+
+/// Sets the catch-up policy copied into future subscriptions on `plan_id`.
+/// Auth: the plan's merchant.
+pub fn do_set_plan_catch_up_policy(
+    env: &Env,
+    plan_id: u32,
+    policy: CatchUpPolicy,
+) -> Result<(), Error> {
+    let mut plan = get_plan(env, plan_id)?;
+    plan.merchant.require_auth();
+
+    validate_catch_up_policy(&policy)?;
+    plan.catch_up = policy.clone();
+    set_persistent(env, &DataKey::Plan(plan_id), &plan);
+    env.events()
+        .publish((Symbol::new(env, "plan_catch_up_updated"), plan_id), policy);
+    Ok(())
+}
+
+/// Creates a subscription on the terms of `plan_id`. Auth: subscriber.
+///
+/// The subscriber cannot choose price, interval, usage mode or catch-up policy;
+/// they are copied from the plan. The first interval is prepaid as with
+/// `create_subscription`, and the plan's trial (if any) starts now.
+pub fn do_subscribe_to_plan(env: &Env, subscriber: Address, plan_id: u32) -> Result<u32, Error> {
+    subscriber.require_auth();
+    let plan = get_plan(env, plan_id)?;
+    open_subscription(
+        env,
+        subscriber,
+        plan.merchant,
+        plan.amount,
+        plan.interval,
+        plan.usage_enabled,
+        plan.amount,
+        plan.trial_seconds,
+        plan.catch_up,
+    )
+}
+
+/// Returns a merchant's plans, paginated by offset in publication order.
+pub fn get_plans_by_merchant(env: &Env, merchant: Address, start: u32, limit: u32) -> Vec<Plan> {
+    let ids: Vec<u32> =
+        get_persistent(env, &DataKey::MerchantPlans(merchant)).unwrap_or(Vec::new(env));
+
+    let mut result = Vec::new(env);
+    let end = start.saturating_add(limit).min(ids.len());
+    let mut i = start;
+    while i < end {
+        if let Ok(plan) = get_plan(env, ids.get(i).unwrap()) {
+            result.push_back(plan);
+        }
+        i += 1;
+    }
+    result
+}
+
+/// Returns the number of plans a merchant has published.
+pub fn get_merchant_plan_count(env: &Env, merchant: Address) -> u32 {
+    let ids: Vec<u32> =
+        get_persistent(env, &DataKey::MerchantPlans(merchant)).unwrap_or(Vec::new(env));
+    ids.len()
+}
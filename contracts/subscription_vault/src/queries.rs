@@ -2,14 +2,15 @@
 //!
 //! **PRs that only add or change read-only/query behavior should edit this file only.**
 
-use crate::types::{DataKey, Error, NextChargeInfo, Subscription, SubscriptionStatus};
+use crate::charge_core::{dunning_key, next_retry_at, period_at, period_start};
+use crate::storage::get_persistent;
+use crate::types::{
+    DataKey, DunningState, Error, NextChargeInfo, Subscription, SubscriptionStatus,
+};
 use soroban_sdk::{Address, Env, Vec};
 
 pub fn get_subscription(env: &Env, subscription_id: u32) -> Result<Subscription, Error> {
-    env.storage()
-        .instance()
-        .get(&subscription_id)
-        .ok_or(Error::NotFound)
+    get_persistent(env, &subscription_id).ok_or(Error::NotFound)
 }
 
 pub fn estimate_topup_for_intervals(
@@ -51,10 +52,7 @@ pub fn get_subscriptions_by_merchant(
     start: u32,
     limit: u32,
 ) -> Vec<Subscription> {
-    let key = DataKey::MerchantSubs(merchant);
-    let ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
-
-    let len = ids.len();
+    let len = get_merchant_subscription_count(env, merchant.clone());
     if start >= len || limit == 0 {
         return Vec::new(env);
     }
@@ -68,8 +66,8 @@ pub fn get_subscriptions_by_merchant(
     let mut result = Vec::new(env);
     let mut i = start;
     while i < end {
-        let sub_id = ids.get(i).unwrap();
-        if let Some(sub) = env.storage().instance().get::<u32, Subscription>(&sub_id) {
+        let sub_id: Option<u32> = get_persistent(env, &DataKey::MerchantSub(merchant.clone(), i));
+        if let Some(sub) = sub_id.and_then(|id| get_persistent::<u32, Subscription>(env, &id)) {
             result.push_back(sub);
         }
         i += 1;
@@ -81,25 +79,55 @@ pub fn get_subscriptions_by_merchant(
 ///
 /// Useful for dashboards and pagination metadata.
 pub fn get_merchant_subscription_count(env: &Env, merchant: Address) -> u32 {
-    let key = DataKey::MerchantSubs(merchant);
-    let ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
-    ids.len()
+    get_persistent(env, &DataKey::MerchantSubCount(merchant)).unwrap_or(0)
+}
+
+/// Returns the merchant's accrued earnings available for withdrawal (0 if none).
+pub fn get_merchant_balance(env: &Env, merchant: Address) -> i128 {
+    get_persistent(env, &DataKey::MerchantBalance(merchant)).unwrap_or(0)
+}
+
+/// Returns [`compute_next_charge_info`] for a stored subscription, reporting the
+/// next dunning retry instead of the interval boundary while one is scheduled.
+pub fn get_next_charge_info(env: &Env, subscription_id: u32) -> Result<NextChargeInfo, Error> {
+    let sub = get_subscription(env, subscription_id)?;
+    let mut info = compute_next_charge_info(&sub);
+    if sub.status == SubscriptionStatus::InsufficientBalance {
+        let state: Option<DunningState> = get_persistent(env, &dunning_key(subscription_id));
+        if let Some(retry_at) =
+            state.and_then(|s| next_retry_at(&s, &crate::admin::get_dunning_schedule(env)))
+        {
+            info.next_charge_timestamp = retry_at;
+        }
+    }
+    Ok(info)
 }
 
 /// Computes the estimated next charge timestamp for a subscription.
 ///
 /// This is a readonly helper that does not mutate contract state. It provides
-/// information for off-chain scheduling systems and UX displays.
+/// information for off-chain scheduling systems and UX displays. While a free
+/// trial is running, the next charge is at the trial end; otherwise it is the
+/// start of the billing period after the one last paid. No charge is expected
+/// once the subscription is set to cancel at period end.
 pub fn compute_next_charge_info(subscription: &Subscription) -> NextChargeInfo {
-    let next_charge_timestamp = subscription
-        .last_payment_timestamp
-        .saturating_add(subscription.interval_seconds);
+    let next_charge_timestamp = subscription.trial_end.unwrap_or_else(|| {
+        match period_at(subscription, subscription.last_payment_timestamp) {
+            Some(period) => period
+                .checked_add(1)
+                .and_then(|next| period_start(subscription, next).ok())
+                .unwrap_or(u64::MAX),
+            // Zero-length interval: no period boundary after the last payment.
+            None => subscription.last_payment_timestamp,
+        }
+    });
 
     let is_charge_expected = match subscription.status {
-        SubscriptionStatus::Active => true,
+        SubscriptionStatus::Active => !subscription.cancel_at_period_end,
         SubscriptionStatus::InsufficientBalance => true,
         SubscriptionStatus::Paused => false,
         SubscriptionStatus::Cancelled => false,
+        SubscriptionStatus::Expired => false,
     };
 
     NextChargeInfo {
@@ -18,7 +18,9 @@ use crate::types::{Error, SubscriptionStatus};
 /// | Paused            | Cancelled           | Yes     |
 /// | InsufficientBalance | Active            | Yes     |
 /// | InsufficientBalance | Cancelled         | Yes     |
+/// | InsufficientBalance | Expired           | Yes     |
 /// | Cancelled         | *any*               | No      |
+/// | Expired           | *any*               | No      |
 /// | *any*             | Same status         | Yes (idempotent) |
 ///
 /// # Arguments
@@ -49,11 +51,13 @@ pub fn validate_status_transition(
                 SubscriptionStatus::Active | SubscriptionStatus::Cancelled
             )
         }
-        SubscriptionStatus::Cancelled => false,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Expired => false,
         SubscriptionStatus::InsufficientBalance => {
             matches!(
                 to,
-                SubscriptionStatus::Active | SubscriptionStatus::Cancelled
+                SubscriptionStatus::Active
+                    | SubscriptionStatus::Cancelled
+                    | SubscriptionStatus::Expired
             )
         }
     };
@@ -76,10 +80,12 @@ pub fn get_allowed_transitions(status: &SubscriptionStatus) -> &'static [Subscri
             SubscriptionStatus::InsufficientBalance,
         ],
         SubscriptionStatus::Paused => &[SubscriptionStatus::Active, SubscriptionStatus::Cancelled],
-        SubscriptionStatus::Cancelled => &[],
-        SubscriptionStatus::InsufficientBalance => {
-            &[SubscriptionStatus::Active, SubscriptionStatus::Cancelled]
-        }
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Expired => &[],
+        SubscriptionStatus::InsufficientBalance => &[
+            SubscriptionStatus::Active,
+            SubscriptionStatus::Cancelled,
+            SubscriptionStatus::Expired,
+        ],
     }
 }
 
@@ -0,0 +1,68 @@
+//! Persistent storage helpers and TTL management.
+//!
+//! Per-subscription records (the `Subscription` itself, its `cp`/`idem` replay keys)
+//! and per-merchant records (`DataKey::MerchantBalance` and the merchant index, one
+//! `DataKey::MerchantSub` entry per subscription plus a count) are unbounded in
+//! number, so they live in persistent storage rather than the contract instance.
+//! Every read or write through these helpers extends the entry's TTL so active
+//! subscriptions are never archived. Contract-wide config stays in instance
+//! storage, whose TTL is extended on every persistent write.
+//!
+//! The bump covers a quarterly interval plus a dunning schedule, so a subscription
+//! touched only by its own charges stays live between them. Subscriptions billed
+//! less often (e.g. `CalendarYears`) must be kept alive by a keeper calling
+//! `extend_subscription_ttl` within every [`PERSISTENT_BUMP_AMOUNT`] ledgers.
+//!
+//! **PRs that only change storage layout or TTL policy should edit this file only.**
+
+use soroban_sdk::{Env, IntoVal, TryFromVal, Val};
+
+/// Approximate number of ledgers closed per day (5s ledgers).
+pub const DAY_IN_LEDGERS: u32 = 17_280;
+/// TTL (in ledgers) that touched entries are extended to: about 120 days.
+pub const PERSISTENT_BUMP_AMOUNT: u32 = 120 * DAY_IN_LEDGERS;
+/// Entries are only extended once their remaining TTL drops below this.
+pub const PERSISTENT_LIFETIME_THRESHOLD: u32 = PERSISTENT_BUMP_AMOUNT - DAY_IN_LEDGERS;
+
+/// Extends the TTL of the contract instance (config and code).
+pub fn extend_instance(env: &Env) {
+    env.storage()
+        .instance()
+        .extend_ttl(PERSISTENT_LIFETIME_THRESHOLD, PERSISTENT_BUMP_AMOUNT);
+}
+
+/// Extends the TTL of a persistent entry. The entry must exist.
+pub fn extend_persistent<K>(env: &Env, key: &K)
+where
+    K: IntoVal<Env, Val>,
+{
+    env.storage().persistent().extend_ttl(
+        key,
+        PERSISTENT_LIFETIME_THRESHOLD,
+        PERSISTENT_BUMP_AMOUNT,
+    );
+}
+
+/// Reads a persistent entry, extending its TTL if present.
+pub fn get_persistent<K, V>(env: &Env, key: &K) -> Option<V>
+where
+    K: IntoVal<Env, Val>,
+    V: TryFromVal<Env, Val>,
+{
+    let value = env.storage().persistent().get(key);
+    if value.is_some() {
+        extend_persistent(env, key);
+    }
+    value
+}
+
+/// Writes a persistent entry and extends its TTL along with the instance's.
+pub fn set_persistent<K, V>(env: &Env, key: &K, value: &V)
+where
+    K: IntoVal<Env, Val>,
+    V: IntoVal<Env, Val>,
+{
+    env.storage().persistent().set(key, value);
+    extend_persistent(env, key);
+    extend_instance(env);
+}
@@ -1,11 +1,22 @@
-//! Subscription lifecycle: create, deposit.
+//! Subscription lifecycle: create, deposit, single charge entrypoints, cancel/refund,
+//! pause, resume.
 //!
 //! **PRs that only change subscription lifecycle or billing should edit this file only.**
+//!
+//! Funds entering the vault are pulled from the subscriber through the token
+//! configured at `init`, so `prepaid_balance` is always backed by tokens held
+//! by the contract.
 
-use crate::queries::get_subscription;
+use crate::charge_core::charge_first_interval;
+use crate::queries::{compute_next_charge_info, get_subscription};
 use crate::state_machine::validate_status_transition;
-use crate::types::{DataKey, Error, Subscription, SubscriptionStatus};
-use soroban_sdk::{Address, Env, Symbol, Vec};
+use crate::storage::{extend_instance, extend_persistent, get_persistent, set_persistent};
+use crate::types::{
+    BatchChargeResult, BillingInterval, CancellationScheduledEvent, CatchUpPolicy, DataKey, Error,
+    LifecycleAction, Subscription, SubscriptionCancelledEvent, SubscriptionCreatedEvent,
+    SubscriptionResumedEvent, SubscriptionStatus, SubscriptionTermsChangedEvent, TermsChange,
+};
+use soroban_sdk::{token, Address, BytesN, Env, Symbol, Vec};
 
 pub fn next_id(env: &Env) -> u32 {
     let key = Symbol::new(env, "next_id");
@@ -14,6 +25,51 @@ pub fn next_id(env: &Env) -> u32 {
     id
 }
 
+/// Transfers `amount` of the vault token from `from` into the contract, to be
+/// credited to a prepaid balance by the caller.
+fn pull_funds(env: &Env, from: &Address, amount: i128) -> Result<(), Error> {
+    crate::admin::require_not_paused(env, |p| p.deposits)?;
+    let token_addr = crate::admin::get_token(env)?;
+    token::Client::new(env, &token_addr).transfer(from, &env.current_contract_address(), &amount);
+    crate::admin::adjust_total_prepaid(env, amount)
+}
+
+/// Checks `amount` and `interval` against [`crate::admin::get_subscription_limits`].
+/// Calendar intervals are measured by their first period starting now.
+pub(crate) fn validate_terms(
+    env: &Env,
+    amount: i128,
+    interval: &BillingInterval,
+) -> Result<(), Error> {
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
+    let seconds = interval
+        .first_period_seconds(env.ledger().timestamp())
+        .ok_or(Error::Overflow)?;
+    if seconds == 0 {
+        return Err(Error::InvalidInterval);
+    }
+    let limits = crate::admin::get_subscription_limits(env);
+    if amount < limits.min_amount || amount > limits.max_amount {
+        return Err(Error::AmountOutOfBounds);
+    }
+    if seconds < limits.min_interval_seconds || seconds > limits.max_interval_seconds {
+        return Err(Error::IntervalOutOfBounds);
+    }
+    Ok(())
+}
+
+/// Requires `merchant` to authorize custom terms. A self-subscription is left
+/// to [`open_subscription`] to reject, as one address cannot authorize twice.
+fn require_merchant_consent(subscriber: &Address, merchant: &Address) {
+    if merchant != subscriber {
+        merchant.require_auth();
+    }
+}
+
+/// Creates a subscription on custom terms. Both parties must authorize, so a
+/// subscriber cannot set their own price; self-serve sign-ups go through plans.
 pub fn do_create_subscription(
     env: &Env,
     subscriber: Address,
@@ -23,28 +79,135 @@ pub fn do_create_subscription(
     usage_enabled: bool,
 ) -> Result<u32, Error> {
     subscriber.require_auth();
+    require_merchant_consent(&subscriber, &merchant);
+    open_subscription(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        BillingInterval::Seconds(interval_seconds),
+        usage_enabled,
+        amount,
+        0,
+        CatchUpPolicy::Forgive,
+    )
+}
+
+/// Creates a subscription funded with `initial_deposit` and charges the first
+/// interval immediately (billing in advance). The deposit must cover at least
+/// one `amount`; otherwise nothing is created and `InsufficientBalance` is returned.
+/// Authorized by both parties, like [`do_create_subscription`].
+pub fn do_create_and_charge_subscription(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval_seconds: u64,
+    usage_enabled: bool,
+    initial_deposit: i128,
+) -> Result<u32, Error> {
+    subscriber.require_auth();
+    require_merchant_consent(&subscriber, &merchant);
+    if initial_deposit < amount {
+        return Err(Error::InsufficientBalance);
+    }
+    let id = open_subscription(
+        env,
+        subscriber,
+        merchant,
+        amount,
+        BillingInterval::Seconds(interval_seconds),
+        usage_enabled,
+        initial_deposit,
+        0,
+        CatchUpPolicy::Forgive,
+    )?;
+    charge_first_interval(env, id)?;
+    Ok(id)
+}
+
+/// Stores a new `Active` subscription with the given terms, pulls `deposit` into
+/// its prepaid balance and indexes it under the merchant. Caller must have
+/// already checked the subscriber's auth. Terms are validated with
+/// [`validate_terms`] and the subscriber may not be the merchant.
+///
+/// The billing anchor is the creation time, or `trial_end` when a non-zero
+/// `trial_seconds` is given; the prepaid first interval is then held, not
+/// charged, until the trial ends.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn open_subscription(
+    env: &Env,
+    subscriber: Address,
+    merchant: Address,
+    amount: i128,
+    interval: BillingInterval,
+    usage_enabled: bool,
+    deposit: i128,
+    trial_seconds: u64,
+    catch_up: CatchUpPolicy,
+) -> Result<u32, Error> {
+    if subscriber == merchant {
+        return Err(Error::SelfSubscription);
+    }
+    validate_terms(env, amount, &interval)?;
+
+    let now = env.ledger().timestamp();
+    let trial_end = if trial_seconds > 0 {
+        Some(now.checked_add(trial_seconds).ok_or(Error::Overflow)?)
+    } else {
+        None
+    };
+
+    pull_funds(env, &subscriber, deposit)?;
+
     let sub = Subscription {
         subscriber: subscriber.clone(),
         merchant: merchant.clone(),
         amount,
-        interval_seconds,
-        last_payment_timestamp: env.ledger().timestamp(),
+        interval: interval.clone(),
+        last_payment_timestamp: now,
+        billing_anchor: trial_end.unwrap_or(now),
         status: SubscriptionStatus::Active,
-        prepaid_balance: 0i128,
+        prepaid_balance: deposit,
         usage_enabled,
+        trial_end,
+        catch_up,
+        cancel_at_period_end: false,
     };
     let id = next_id(env);
-    env.storage().instance().set(&id, &sub);
+    set_persistent(env, &id, &sub);
 
-    // Maintain merchant → subscription-ID index
-    let key = DataKey::MerchantSubs(sub.merchant.clone());
-    let mut ids: Vec<u32> = env.storage().instance().get(&key).unwrap_or(Vec::new(env));
-    ids.push_back(id);
-    env.storage().instance().set(&key, &ids);
+    // Maintain merchant → subscription-ID index, one entry per subscription
+    let count_key = DataKey::MerchantSubCount(sub.merchant.clone());
+    let position: u32 = get_persistent(env, &count_key).unwrap_or(0);
+    set_persistent(
+        env,
+        &DataKey::MerchantSub(sub.merchant.clone(), position),
+        &id,
+    );
+    set_persistent(env, &DataKey::MerchantSubPosition(id), &position);
+    set_persistent(
+        env,
+        &count_key,
+        &position.checked_add(1).ok_or(Error::Overflow)?,
+    );
 
+    env.events().publish(
+        (Symbol::new(env, "created"), id),
+        SubscriptionCreatedEvent {
+            subscription_id: id,
+            subscriber,
+            merchant,
+            amount,
+            interval,
+        },
+    );
     Ok(id)
 }
 
+/// Adds `amount` to the prepaid balance. If the subscription is in
+/// `InsufficientBalance` and the new balance covers at least one `amount`, it is
+/// moved back to `Active` and a `resumed` event is published.
 pub fn do_deposit_funds(
     env: &Env,
     subscription_id: u32,
@@ -53,24 +216,316 @@ pub fn do_deposit_funds(
 ) -> Result<(), Error> {
     subscriber.require_auth();
 
+    if amount <= 0 {
+        return Err(Error::InvalidAmount);
+    }
     let min_topup: i128 = crate::admin::get_min_topup(env)?;
     if amount < min_topup {
         return Err(Error::BelowMinimumTopup);
     }
 
     let mut sub = get_subscription(env, subscription_id)?;
+    pull_funds(env, &subscriber, amount)?;
+
     sub.prepaid_balance = sub
         .prepaid_balance
         .checked_add(amount)
         .ok_or(Error::Overflow)?;
-    env.storage().instance().set(&subscription_id, &sub);
+
+    let reactivated =
+        sub.status == SubscriptionStatus::InsufficientBalance && sub.prepaid_balance >= sub.amount;
+    if reactivated {
+        validate_status_transition(&sub.status, &SubscriptionStatus::Active)?;
+        sub.status = SubscriptionStatus::Active;
+        env.storage()
+            .persistent()
+            .remove(&crate::charge_core::dunning_key(subscription_id));
+    }
+
+    set_persistent(env, &subscription_id, &sub);
     env.events().publish(
         (Symbol::new(env, "deposited"), subscription_id),
-        (subscriber, amount, sub.prepaid_balance),
+        (subscriber.clone(), amount, sub.prepaid_balance),
+    );
+    if reactivated {
+        env.events().publish(
+            (Symbol::new(env, "resumed"), subscription_id),
+            SubscriptionResumedEvent {
+                subscription_id,
+                authorizer: subscriber,
+            },
+        );
+    }
+    Ok(())
+}
+
+/// Changes how missed intervals are charged for this subscription. Since it
+/// alters the billing agreement, both subscriber and merchant must authorize.
+pub fn do_set_catch_up_policy(
+    env: &Env,
+    subscription_id: u32,
+    policy: CatchUpPolicy,
+) -> Result<(), Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    sub.subscriber.require_auth();
+    sub.merchant.require_auth();
+
+    validate_catch_up_policy(&policy)?;
+    sub.catch_up = policy.clone();
+    set_persistent(env, &subscription_id, &sub);
+    env.events().publish(
+        (Symbol::new(env, "catch_up_updated"), subscription_id),
+        policy,
+    );
+    Ok(())
+}
+
+/// Rejects `ChargeUpTo(0)`, which would never charge anything.
+pub(crate) fn validate_catch_up_policy(policy: &CatchUpPolicy) -> Result<(), Error> {
+    if *policy == CatchUpPolicy::ChargeUpTo(0) {
+        return Err(Error::InvalidAmount);
+    }
+    Ok(())
+}
+
+/// Moves an `Active` subscription to a new price, either set directly or taken
+/// from a plan of the same merchant on the same interval (which also supplies
+/// the usage mode and catch-up policy).
+///
+/// If the current period has already been charged, the price difference for its
+/// unused part (from now to the next charge, relative to `last_payment_timestamp`)
+/// is settled at once: an increase is debited from `prepaid_balance` and credited
+/// to the merchant, a decrease is refunded from the merchant's ledger. The refund
+/// is capped at the merchant's unwithdrawn earnings, so it is always backed by
+/// tokens the vault still holds.
+///
+/// Auth: subscriber and merchant for a price increase; otherwise the subscriber
+/// when moving to a plan, or the merchant when setting an amount.
+pub fn do_change_subscription_terms(
+    env: &Env,
+    subscription_id: u32,
+    change: TermsChange,
+) -> Result<(), Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    if sub.status != SubscriptionStatus::Active {
+        return Err(Error::NotActive);
+    }
+
+    let (new_amount, plan_id) = match change {
+        TermsChange::Amount(amount) => (amount, None),
+        TermsChange::Plan(plan_id) => {
+            let plan = crate::plans::get_plan(env, plan_id)?;
+            if plan.merchant != sub.merchant || plan.interval != sub.interval {
+                return Err(Error::IncompatiblePlan);
+            }
+            sub.usage_enabled = plan.usage_enabled;
+            sub.catch_up = plan.catch_up;
+            (plan.amount, Some(plan_id))
+        }
+    };
+
+    let old_amount = sub.amount;
+    if new_amount > old_amount {
+        sub.subscriber.require_auth();
+        sub.merchant.require_auth();
+    } else if plan_id.is_some() {
+        sub.subscriber.require_auth();
+    } else {
+        sub.merchant.require_auth();
+    }
+    validate_terms(env, new_amount, &sub.interval)?;
+
+    let mut proration = prorated_difference(env, subscription_id, &sub, new_amount)?;
+    if proration != 0 {
+        crate::admin::require_not_paused(env, |p| p.charging)?;
+    }
+    if proration > 0 {
+        if sub.prepaid_balance < proration {
+            return Err(Error::InsufficientBalance);
+        }
+        sub.prepaid_balance -= proration;
+        crate::admin::adjust_total_prepaid(env, -proration)?;
+        crate::merchant::credit_merchant_balance(env, &sub.merchant, proration)?;
+    } else if proration < 0 {
+        let earned = crate::queries::get_merchant_balance(env, sub.merchant.clone());
+        proration = proration.max(-earned);
+        crate::merchant::debit_merchant_balance(env, &sub.merchant, -proration)?;
+        sub.prepaid_balance = sub
+            .prepaid_balance
+            .checked_sub(proration)
+            .ok_or(Error::Overflow)?;
+        crate::admin::adjust_total_prepaid(env, -proration)?;
+    }
+
+    sub.amount = new_amount;
+    set_persistent(env, &subscription_id, &sub);
+    env.events().publish(
+        (Symbol::new(env, "terms_changed"), subscription_id),
+        SubscriptionTermsChangedEvent {
+            subscription_id,
+            old_amount,
+            new_amount,
+            plan_id,
+            proration,
+        },
     );
     Ok(())
 }
 
+/// Price difference for the unused part of the current period, rounded toward
+/// zero. Zero unless the period containing now has already been charged.
+fn prorated_difference(
+    env: &Env,
+    subscription_id: u32,
+    sub: &Subscription,
+    new_amount: i128,
+) -> Result<i128, Error> {
+    let now = env.ledger().timestamp();
+    let charged: Option<u64> = get_persistent(
+        env,
+        &crate::charge_core::charged_period_key(subscription_id),
+    );
+    let current = crate::charge_core::period_at(sub, now);
+    if charged.is_none() || charged != current {
+        return Ok(0);
+    }
+
+    let start = sub.last_payment_timestamp;
+    let end = compute_next_charge_info(sub).next_charge_timestamp;
+    if now >= end || end <= start {
+        return Ok(0);
+    }
+    let unused = i128::from(end - now);
+    let length = i128::from(end - start);
+    new_amount
+        .checked_sub(sub.amount)
+        .and_then(|diff| diff.checked_mul(unused))
+        .map(|total| total / length)
+        .ok_or(Error::Overflow)
+}
+
+/// Deposits like [`do_deposit_funds`], then immediately charges the interval
+/// that is due (e.g. the one missed while underfunded), if the subscription is
+/// `Active`, the charge is due and the balance covers it. While charging is
+/// paused only the deposit is made.
+pub fn do_deposit_and_settle(
+    env: &Env,
+    subscription_id: u32,
+    subscriber: Address,
+    amount: i128,
+) -> Result<(), Error> {
+    do_deposit_funds(env, subscription_id, subscriber, amount)?;
+
+    let sub = get_subscription(env, subscription_id)?;
+    let due = compute_next_charge_info(&sub).next_charge_timestamp <= env.ledger().timestamp();
+    if !crate::admin::get_pause_flags(env).charging
+        && sub.status == SubscriptionStatus::Active
+        && !sub.cancel_at_period_end
+        && due
+        && sub.prepaid_balance >= sub.amount
+    {
+        crate::charge_core::charge_one(env, subscription_id, None)?;
+    }
+    Ok(())
+}
+
+/// Charges one interval. Requires auth from the billing operator (or admin if unset).
+///
+/// A charge that fails for lack of funds still moves the subscription to
+/// `InsufficientBalance` (or advances its dunning state), so it is reported as
+/// `Ok` with `success: false` rather than as an error, which would roll that back.
+pub fn do_charge_subscription(
+    env: &Env,
+    subscription_id: u32,
+    idempotency_key: Option<BytesN<32>>,
+) -> Result<BatchChargeResult, Error> {
+    crate::admin::require_billing_operator(env)?.require_auth();
+    match crate::charge_core::charge_one(env, subscription_id, idempotency_key) {
+        Ok(()) => Ok(BatchChargeResult {
+            success: true,
+            error_code: 0,
+        }),
+        Err(Error::InsufficientBalance) => Ok(BatchChargeResult {
+            success: false,
+            error_code: Error::InsufficientBalance.to_code(),
+        }),
+        Err(e) => Err(e),
+    }
+}
+
+/// Debits a metered usage amount. Requires auth from the billing operator (or admin if unset).
+pub fn do_charge_usage(
+    env: &Env,
+    subscription_id: u32,
+    usage_amount: i128,
+    report_seq: u64,
+    idempotency_key: Option<BytesN<32>>,
+) -> Result<(), Error> {
+    crate::admin::require_billing_operator(env)?.require_auth();
+    crate::charge_core::charge_usage_one(
+        env,
+        subscription_id,
+        usage_amount,
+        report_seq,
+        idempotency_key,
+    )
+}
+
+/// Extends the TTL of a subscription, its replay-protection entries and its
+/// merchant's index and balance. Callable by anyone, since whoever calls pays the rent.
+pub fn do_extend_subscription_ttl(env: &Env, subscription_id: u32) -> Result<(), Error> {
+    let sub = get_subscription(env, subscription_id)?;
+
+    for key in [
+        crate::charge_core::charged_period_key(subscription_id),
+        crate::charge_core::idem_key(subscription_id),
+        crate::charge_core::usage_idem_key(subscription_id),
+        crate::charge_core::usage_seq_key(subscription_id),
+        crate::charge_core::dunning_key(subscription_id),
+    ] {
+        if env.storage().persistent().has(&key) {
+            extend_persistent(env, &key);
+        }
+    }
+    let mut merchant_keys = Vec::from_array(
+        env,
+        [
+            DataKey::MerchantSubCount(sub.merchant.clone()),
+            DataKey::MerchantBalance(sub.merchant.clone()),
+        ],
+    );
+    if let Some(position) = get_persistent(env, &DataKey::MerchantSubPosition(subscription_id)) {
+        merchant_keys.push_back(DataKey::MerchantSub(sub.merchant, position));
+    }
+    for key in merchant_keys {
+        if env.storage().persistent().has(&key) {
+            extend_persistent(env, &key);
+        }
+    }
+    extend_instance(env);
+    Ok(())
+}
+
+/// Checks `authorizer` against the configured [`crate::types::ActionPolicy`] for `action`.
+fn require_lifecycle_authorizer(
+    env: &Env,
+    sub: &Subscription,
+    authorizer: &Address,
+    action: LifecycleAction,
+) -> Result<(), Error> {
+    let policy = crate::admin::get_action_policy(env, action);
+    if policy.subscriber && *authorizer == sub.subscriber {
+        return Ok(());
+    }
+    if policy.merchant && *authorizer == sub.merchant {
+        return Ok(());
+    }
+    if policy.admin && crate::admin::require_admin(env).is_ok_and(|a| a == *authorizer) {
+        return Ok(());
+    }
+    Err(Error::Unauthorized)
+}
+
 pub fn do_cancel_subscription(
     env: &Env,
     subscription_id: u32,
@@ -79,11 +534,119 @@ pub fn do_cancel_subscription(
     authorizer.require_auth();
 
     let mut sub = get_subscription(env, subscription_id)?;
+    require_lifecycle_authorizer(env, &sub, &authorizer, LifecycleAction::Cancel)?;
     validate_status_transition(&sub.status, &SubscriptionStatus::Cancelled)?;
     sub.status = SubscriptionStatus::Cancelled;
 
-    // TODO: allow withdraw of prepaid_balance
-    env.storage().instance().set(&subscription_id, &sub);
+    // Remaining prepaid_balance stays in the vault until the subscriber calls
+    // `withdraw_subscriber_funds`.
+    set_persistent(env, &subscription_id, &sub);
+    env.storage()
+        .persistent()
+        .remove(&crate::charge_core::dunning_key(subscription_id));
+    Ok(())
+}
+
+/// Schedules an `Active` subscription to be cancelled when its next charge is
+/// due, instead of charging it. Authorized like [`do_cancel_subscription`].
+pub fn do_cancel_at_period_end(
+    env: &Env,
+    subscription_id: u32,
+    authorizer: Address,
+) -> Result<(), Error> {
+    authorizer.require_auth();
+
+    let mut sub = get_subscription(env, subscription_id)?;
+    require_lifecycle_authorizer(env, &sub, &authorizer, LifecycleAction::Cancel)?;
+    if sub.status != SubscriptionStatus::Active {
+        return Err(Error::NotActive);
+    }
+    let cancel_at = compute_next_charge_info(&sub).next_charge_timestamp;
+    if env.ledger().timestamp() >= cancel_at {
+        return Err(Error::PeriodEnded);
+    }
+    sub.cancel_at_period_end = true;
+
+    set_persistent(env, &subscription_id, &sub);
+    env.events().publish(
+        (Symbol::new(env, "cancel_scheduled"), subscription_id),
+        CancellationScheduledEvent {
+            subscription_id,
+            authorizer,
+            cancel_at,
+        },
+    );
+    Ok(())
+}
+
+/// Withdraws a cancellation scheduled by [`do_cancel_at_period_end`] while the
+/// current period is still running, so billing continues as normal. A no-op if
+/// none is scheduled.
+pub fn do_undo_cancel_at_period_end(
+    env: &Env,
+    subscription_id: u32,
+    authorizer: Address,
+) -> Result<(), Error> {
+    authorizer.require_auth();
+
+    let mut sub = get_subscription(env, subscription_id)?;
+    require_lifecycle_authorizer(env, &sub, &authorizer, LifecycleAction::Cancel)?;
+    if !sub.cancel_at_period_end {
+        return Ok(());
+    }
+    if env.ledger().timestamp() >= compute_next_charge_info(&sub).next_charge_timestamp {
+        return Err(Error::PeriodEnded);
+    }
+    sub.cancel_at_period_end = false;
+
+    set_persistent(env, &subscription_id, &sub);
+    env.events().publish(
+        (Symbol::new(env, "cancel_unscheduled"), subscription_id),
+        authorizer,
+    );
+    Ok(())
+}
+
+/// Returns the unused prepaid balance of a `Cancelled` or `Expired` subscription
+/// to its subscriber.
+///
+/// Zeroes `prepaid_balance` before transferring. Calling again once the balance
+/// is zero is a no-op.
+pub fn do_withdraw_subscriber_funds(env: &Env, subscription_id: u32) -> Result<(), Error> {
+    let mut sub = get_subscription(env, subscription_id)?;
+    sub.subscriber.require_auth();
+
+    if !matches!(
+        sub.status,
+        SubscriptionStatus::Cancelled | SubscriptionStatus::Expired
+    ) {
+        return Err(Error::NotCancelled);
+    }
+
+    let refund_amount = sub.prepaid_balance;
+    if refund_amount == 0 {
+        return Ok(());
+    }
+
+    sub.prepaid_balance = 0;
+    crate::admin::adjust_total_prepaid(env, -refund_amount)?;
+    set_persistent(env, &subscription_id, &sub);
+
+    let token_addr = crate::admin::get_token(env)?;
+    token::Client::new(env, &token_addr).transfer(
+        &env.current_contract_address(),
+        &sub.subscriber,
+        &refund_amount,
+    );
+
+    env.events().publish(
+        (Symbol::new(env, "refunded"), subscription_id),
+        SubscriptionCancelledEvent {
+            subscription_id,
+            authorizer: sub.subscriber,
+            refund_amount,
+        },
+    );
     Ok(())
 }
 
@@ -95,13 +658,19 @@ pub fn do_pause_subscription(
     authorizer.require_auth();
 
     let mut sub = get_subscription(env, subscription_id)?;
+    require_lifecycle_authorizer(env, &sub, &authorizer, LifecycleAction::Pause)?;
     validate_status_transition(&sub.status, &SubscriptionStatus::Paused)?;
     sub.status = SubscriptionStatus::Paused;
 
-    env.storage().instance().set(&subscription_id, &sub);
+    set_persistent(env, &subscription_id, &sub);
     Ok(())
 }
 
+/// Moves a `Paused` or `InsufficientBalance` subscription back to `Active`. An
+/// `InsufficientBalance` subscription can only resume once its prepaid balance
+/// covers one `amount` ([`Error::InsufficientBalance`] otherwise), so resuming
+/// cannot restart dunning from scratch; periods it missed stay due. Periods
+/// spent `Paused` are skipped, so the next charge covers only the current one.
 pub fn do_resume_subscription(
     env: &Env,
     subscription_id: u32,
@@ -110,9 +679,19 @@ pub fn do_resume_subscription(
     authorizer.require_auth();
 
     let mut sub = get_subscription(env, subscription_id)?;
+    require_lifecycle_authorizer(env, &sub, &authorizer, LifecycleAction::Resume)?;
     validate_status_transition(&sub.status, &SubscriptionStatus::Active)?;
+    if sub.status == SubscriptionStatus::InsufficientBalance && sub.prepaid_balance < sub.amount {
+        return Err(Error::InsufficientBalance);
+    }
+    if sub.status == SubscriptionStatus::Paused {
+        crate::charge_core::skip_paused_periods(env, subscription_id, &mut sub)?;
+    }
     sub.status = SubscriptionStatus::Active;
 
-    env.storage().instance().set(&subscription_id, &sub);
+    set_persistent(env, &subscription_id, &sub);
+    env.storage()
+        .persistent()
+        .remove(&crate::charge_core::dunning_key(subscription_id));
     Ok(())
 }
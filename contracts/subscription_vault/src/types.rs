@@ -3,14 +3,28 @@
 //! Kept in a separate module to reduce merge conflicts when editing state machine
 //! or contract entrypoints.
 
-use soroban_sdk::{contracterror, contracttype, Address};
+use soroban_sdk::{contracterror, contracttype, Address, BytesN, Vec};
 
-/// Storage keys for secondary indices.
+/// Storage keys for secondary indices and per-merchant ledgers (persistent storage).
 #[contracttype]
 #[derive(Clone)]
 pub enum DataKey {
-    /// Maps a merchant address to its list of subscription IDs.
-    MerchantSubs(Address),
+    /// Number of subscriptions indexed for a merchant.
+    MerchantSubCount(Address),
+    /// Maps a merchant address and index position to a subscription ID.
+    MerchantSub(Address, u32),
+    /// Maps a subscription ID to its position in its merchant's index.
+    MerchantSubPosition(u32),
+    /// Maps a merchant address to its accrued, not yet withdrawn, earnings.
+    MerchantBalance(Address),
+    /// Who may authorize a lifecycle action (see [`ActionPolicy`]).
+    ActionPolicy(LifecycleAction),
+    /// A merchant-published [`Plan`] by plan ID.
+    Plan(u32),
+    /// Maps a merchant address to its list of plan IDs.
+    MerchantPlans(Address),
+    /// A [`CouncilProposal`] by proposal ID.
+    CouncilProposal(u32),
 }
 
 #[contracterror]
@@ -19,7 +33,7 @@ pub enum DataKey {
 pub enum Error {
     NotFound = 404,
     Unauthorized = 401,
-    /// Charge attempted before `last_payment_timestamp + interval_seconds`.
+    /// Charge attempted before the next billing period starts.
     IntervalNotElapsed = 1001,
     /// Subscription is not Active (e.g. Paused, Cancelled).
     NotActive = 1002,
@@ -39,6 +53,52 @@ pub enum Error {
     Replay = 1007,
     /// Recovery amount is zero or negative.
     InvalidRecoveryAmount = 1008,
+    /// Merchant withdrawal exceeds the merchant's accrued balance.
+    InsufficientMerchantBalance = 1009,
+    /// Operation requires the subscription to be `Cancelled`.
+    NotCancelled = 1010,
+    /// Token is not the one this vault holds.
+    InvalidToken = 1011,
+    /// Charge attempted before the subscription's free trial ends.
+    TrialActive = 1012,
+    /// Dunning retry offsets must be non-zero and strictly increasing.
+    InvalidDunningSchedule = 1013,
+    /// Billing interval is zero-length.
+    InvalidInterval = 1014,
+    /// Subscriber and merchant are the same address.
+    SelfSubscription = 1015,
+    /// Amount is outside the admin-configured [`SubscriptionLimits`].
+    AmountOutOfBounds = 1016,
+    /// Interval is outside the admin-configured [`SubscriptionLimits`].
+    IntervalOutOfBounds = 1017,
+    /// Limits must have positive minimums and `min <= max`.
+    InvalidLimits = 1018,
+    /// Plan belongs to another merchant or bills on a different interval.
+    IncompatiblePlan = 1019,
+    /// The period a cancellation was scheduled for has already ended.
+    PeriodEnded = 1020,
+    /// The operation is halted by the admin's emergency [`PauseFlags`].
+    ContractPaused = 1021,
+    /// The pending admin proposal has expired (or the expiry given is not in the future).
+    AdminProposalExpired = 1022,
+    /// Queued config change `eta` is sooner than the timelock delay allows.
+    EtaTooEarly = 1023,
+    /// Queued config change executed before its `eta`.
+    TimelockNotElapsed = 1024,
+    /// Config changes must be queued while a timelock delay is set.
+    TimelockRequired = 1025,
+    /// Admin actions must go through the admin council.
+    CouncilRequired = 1026,
+    /// Council threshold must be between 1 and the number of distinct signers.
+    InvalidCouncil = 1027,
+    /// The signer has already approved this council proposal.
+    AlreadyApproved = 1028,
+    /// The council proposal has already been executed.
+    ProposalAlreadyExecuted = 1029,
+    /// Recovery exceeds the vault's surplus over subscriber and merchant balances.
+    ExceedsSurplus = 1030,
+    /// `init` called on a vault that is already initialized.
+    AlreadyInitialized = 1031,
 }
 
 impl Error {
@@ -58,13 +118,37 @@ impl Error {
             Error::InvalidAmount => 1006,
             Error::Replay => 1007,
             Error::InvalidRecoveryAmount => 1008,
+            Error::InsufficientMerchantBalance => 1009,
+            Error::NotCancelled => 1010,
+            Error::InvalidToken => 1011,
+            Error::TrialActive => 1012,
+            Error::InvalidDunningSchedule => 1013,
+            Error::InvalidInterval => 1014,
+            Error::SelfSubscription => 1015,
+            Error::AmountOutOfBounds => 1016,
+            Error::IntervalOutOfBounds => 1017,
+            Error::InvalidLimits => 1018,
+            Error::IncompatiblePlan => 1019,
+            Error::PeriodEnded => 1020,
+            Error::ContractPaused => 1021,
+            Error::AdminProposalExpired => 1022,
+            Error::EtaTooEarly => 1023,
+            Error::TimelockNotElapsed => 1024,
+            Error::TimelockRequired => 1025,
+            Error::CouncilRequired => 1026,
+            Error::InvalidCouncil => 1027,
+            Error::AlreadyApproved => 1028,
+            Error::ProposalAlreadyExecuted => 1029,
+            Error::ExceedsSurplus => 1030,
+            Error::AlreadyInitialized => 1031,
         }
     }
 }
 
-/// Result of charging one subscription in a batch. Used by [`crate::SubscriptionVault::batch_charge`].
+/// Result of charging one subscription. Used by [`crate::SubscriptionVault::batch_charge`]
+/// per item and by [`crate::SubscriptionVault::charge_subscription`].
 #[contracttype]
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, Eq, PartialEq)]
 pub struct BatchChargeResult {
     /// True if the charge succeeded.
     pub success: bool,
@@ -88,7 +172,11 @@ pub struct BatchChargeResult {
 ///   - No outgoing transitions (terminal state)
 ///
 /// - **InsufficientBalance**: Subscription failed due to insufficient funds.
-///   - Can transition to: `Active` (after deposit), `Cancelled`
+///   - Can transition to: `Active` (after deposit or a successful dunning retry),
+///     `Cancelled`, `Expired`
+///
+/// - **Expired**: The final dunning retry failed; only a refund is possible.
+///   - No outgoing transitions (terminal state)
 ///
 /// Invalid transitions (e.g., `Cancelled` -> `Active`) are rejected with
 /// [`Error::InvalidStatusTransition`].
@@ -103,6 +191,27 @@ pub enum SubscriptionStatus {
     Cancelled = 2,
     /// Subscription failed due to insufficient balance for charging.
     InsufficientBalance = 3,
+    /// Subscription lapsed after its final dunning retry failed (terminal state).
+    Expired = 4,
+}
+
+/// Subscription lifecycle actions whose authorizers are governed by an [`ActionPolicy`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum LifecycleAction {
+    Pause = 0,
+    Resume = 1,
+    Cancel = 2,
+}
+
+/// Which parties may authorize a [`LifecycleAction`]. Set by admin; when unset
+/// the default is subscriber or merchant, not admin.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ActionPolicy {
+    pub subscriber: bool,
+    pub merchant: bool,
+    pub admin: bool,
 }
 
 /// Stores subscription details and current state.
@@ -115,12 +224,181 @@ pub struct Subscription {
     pub subscriber: Address,
     pub merchant: Address,
     pub amount: i128,
-    pub interval_seconds: u64,
+    pub interval: BillingInterval,
     pub last_payment_timestamp: u64,
+    /// Start of billing period 0; period `k` starts at
+    /// `billing_anchor` plus `k` intervals. Fixed for the subscription's life.
+    pub billing_anchor: u64,
     /// Current lifecycle state. Modified only through state machine transitions.
     pub status: SubscriptionStatus,
     pub prepaid_balance: i128,
     pub usage_enabled: bool,
+    /// End of the free trial, if any. No interval charge is allowed before it;
+    /// the first charge at or after it converts the trial and clears this field.
+    pub trial_end: Option<u64>,
+    /// How missed intervals are charged; see [`CatchUpPolicy`].
+    pub catch_up: CatchUpPolicy,
+    /// When set, the subscription stays `Active` until its next charge is due
+    /// and is then `Cancelled` instead of charged.
+    pub cancel_at_period_end: bool,
+}
+
+/// Length of a billing period. Calendar intervals keep the anchor's day of month
+/// (clamped to the month's last day) and time of day, in UTC.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum BillingInterval {
+    /// A fixed number of seconds.
+    Seconds(u64),
+    /// A fixed number of 24-hour days.
+    Days(u32),
+    /// Every N calendar months, e.g. the 1st of every month.
+    CalendarMonths(u32),
+    /// Every N calendar years, on the same calendar date.
+    CalendarYears(u32),
+}
+
+/// How an interval charge handles intervals that were missed (e.g. while the
+/// billing keeper was down).
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CatchUpPolicy {
+    /// Charge one interval and forgive the rest; `last_payment_timestamp` moves to
+    /// the start of the current period, so billing dates stay on the anchor.
+    Forgive,
+    /// Charge every missed interval the prepaid balance covers.
+    ChargeAll,
+    /// Charge at most this many missed intervals; any beyond are forgiven.
+    ChargeUpTo(u32),
+}
+
+/// New terms for `change_subscription_terms`: a new price, or the terms of a
+/// plan from the same merchant on the same interval.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum TermsChange {
+    Amount(i128),
+    Plan(u32),
+}
+
+/// Dunning progress of an `InsufficientBalance` subscription: when the first
+/// charge failed and how many scheduled retries have failed since.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DunningState {
+    pub failed_at: u64,
+    pub attempts: u32,
+}
+
+/// A config change that can be queued behind the admin timelock.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ConfigChange {
+    MinTopup(i128),
+    BillingOperator(Address),
+    ActionPolicy(LifecycleAction, ActionPolicy),
+    DunningSchedule(Vec<u64>),
+    SubscriptionLimits(SubscriptionLimits),
+    /// Minimum notice, in seconds, for later queued changes.
+    TimelockDelay(u64),
+}
+
+/// A [`ConfigChange`] queued by admin, executable by anyone from `eta`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct QueuedConfigChange {
+    pub id: u32,
+    pub change: ConfigChange,
+    pub eta: u64,
+}
+
+/// Signers that jointly hold admin power; `threshold` approvals execute an action.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct AdminCouncil {
+    pub signers: Vec<Address>,
+    pub threshold: u32,
+}
+
+/// An admin action the council can vote on.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CouncilAction {
+    /// `recover_stranded_funds(recipient, amount, reason)`.
+    RecoverFunds(Address, i128, RecoveryReason),
+    /// Propose a single admin; the council is dissolved once they call
+    /// `accept_admin`. Replaces any pending proposal.
+    SetAdmin(Address),
+    /// Replace the signers and threshold.
+    SetCouncil(AdminCouncil),
+    /// Apply a config change, queued behind the timelock delay if one is set.
+    Config(ConfigChange),
+    /// `cancel_config_change(id)`.
+    CancelConfigChange(u32),
+    SetPauseFlags(PauseFlags),
+}
+
+/// A council action collecting approvals.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct CouncilProposal {
+    pub action: CouncilAction,
+    pub proposer: Address,
+    pub approvals: Vec<Address>,
+    pub executed: bool,
+}
+
+/// An admin transfer awaiting acceptance by `admin`, optionally valid only
+/// before `expires_at`.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct PendingAdmin {
+    pub admin: Address,
+    pub expires_at: Option<u64>,
+}
+
+/// Contract-wide emergency stops, set by admin. Subscriber refunds of cancelled
+/// or expired subscriptions are never paused.
+#[contracttype]
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct PauseFlags {
+    /// Halts interval, usage and proration charges (including `batch_charge`).
+    pub charging: bool,
+    /// Halts every transfer into the vault: deposits and subscription creation.
+    pub deposits: bool,
+    /// Halts merchant withdrawals.
+    pub withdrawals: bool,
+}
+
+/// Bounds on the terms of new subscriptions and plans, set by admin. Calendar
+/// intervals are checked by the length of their first period.
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SubscriptionLimits {
+    pub min_amount: i128,
+    pub max_amount: i128,
+    pub min_interval_seconds: u64,
+    pub max_interval_seconds: u64,
+}
+
+/// Subscription terms published by a merchant. Subscribers join a plan via
+/// `subscribe_to_plan`, which copies these terms into a new [`Subscription`].
+#[contracttype]
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Plan {
+    pub merchant: Address,
+    /// Price charged per interval.
+    pub amount: i128,
+    pub interval: BillingInterval,
+    pub usage_enabled: bool,
+    /// Length of the free trial granted to new subscribers (0 for none).
+    pub trial_seconds: u64,
+    /// Catch-up policy copied into subscriptions on this plan.
+    pub catch_up: CatchUpPolicy,
+    /// Token the plan is priced in; must be the vault's token.
+    pub token: Address,
+    /// Hash of off-chain plan metadata (name, description, features).
+    pub metadata_hash: BytesN<32>,
 }
 
 // Event types
@@ -131,7 +409,7 @@ pub struct SubscriptionCreatedEvent {
     pub subscriber: Address,
     pub merchant: Address,
     pub amount: i128,
-    pub interval_seconds: u64,
+    pub interval: BillingInterval,
 }
 
 #[contracttype]
@@ -150,6 +428,26 @@ pub struct SubscriptionChargedEvent {
     pub amount: i128,
 }
 
+/// Emitted when the first paid charge after a free trial succeeds.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct TrialConvertedEvent {
+    pub subscription_id: u32,
+    pub trial_end: u64,
+    pub amount: i128,
+}
+
+/// Emitted when a subscription expires after its final dunning retry fails.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionExpiredEvent {
+    pub subscription_id: u32,
+    pub failed_at: u64,
+    pub attempts: u32,
+}
+
+/// Emitted when the remaining prepaid balance of a cancelled subscription is
+/// returned to the subscriber.
 #[contracttype]
 #[derive(Clone, Debug)]
 pub struct SubscriptionCancelledEvent {
@@ -158,6 +456,29 @@ pub struct SubscriptionCancelledEvent {
     pub refund_amount: i128,
 }
 
+/// Emitted when a subscription's price changes. `proration` is the amount
+/// charged to the subscriber for the rest of the current period (negative
+/// when refunded to the prepaid balance, capped at the merchant's earnings).
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct SubscriptionTermsChangedEvent {
+    pub subscription_id: u32,
+    pub old_amount: i128,
+    pub new_amount: i128,
+    pub plan_id: Option<u32>,
+    pub proration: i128,
+}
+
+/// Emitted when a subscription is set to cancel at the end of its current
+/// period; `cancel_at` is when the next charge would have been due.
+#[contracttype]
+#[derive(Clone, Debug)]
+pub struct CancellationScheduledEvent {
+    pub subscription_id: u32,
+    pub authorizer: Address,
+    pub cancel_at: u64,
+}
+
 #[contracttype]
 #[derive(Clone, Debug)]
 pub struct SubscriptionPausedEvent {